@@ -3,27 +3,98 @@ use std::fmt::Write as _;
 use anyhow::{anyhow, Context, Result};
 
 use crate::{
+    classes::Mode,
     instructor::{Instructor, InstructorId},
     session::{Session, SessionId},
     talloc::{Availability, TallocApplication, TallocApps},
+    utils::{Day, TimeOfDay},
 };
 
+#[derive(Clone)]
 pub struct AvailabilityMatrix {
     num_instructors: usize,
     availability_session_x_instructor: Vec<Availability>,
+    // sessions which are impossible for an instructor only because of some of their
+    // hours, with whether each hour of the session is possible
+    partial_availabilities: Vec<(SessionId, InstructorId, Vec<bool>)>,
 }
 
-fn check_availability(application: TallocApplication, session: &Session) -> Option<Availability> {
-    (0..session.duration.hours())
-        .map(|hour_offset| {
-            application.get_availability(
-                session.day,
-                session.start_time.add_hr(hour_offset),
-                session.mode,
-            )
-        })
-        .min()
-        .flatten()
+// How the availabilities for each hour of a multi-hour session are combined. An
+// impossible hour always makes the whole session impossible, since the instructor
+// can't teach it at all.
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum HourCombinePolicy {
+    #[default]
+    Min,
+    Average,
+    Majority,
+}
+
+impl HourCombinePolicy {
+    fn combine(self, hours: &[Availability]) -> Option<Availability> {
+        if hours.contains(&Availability::Impossible) {
+            return Some(Availability::Impossible);
+        }
+
+        match self {
+            HourCombinePolicy::Min => hours.iter().copied().min(),
+            HourCombinePolicy::Average => {
+                let num_hours = hours.len() as u32;
+                if num_hours == 0 {
+                    return None;
+                }
+                let total = hours.iter().map(|&hour| hour as u32).sum::<u32>();
+                // round to the nearest level, with halves rounding down
+                Availability::from_level(((2 * total + num_hours - 1) / (2 * num_hours)) as u8)
+            }
+            // the most common level, with ties going to the worse level
+            HourCombinePolicy::Majority => hours.iter().copied().max_by_key(|&level| {
+                (
+                    hours.iter().filter(|&&other| other == level).count(),
+                    std::cmp::Reverse(level),
+                )
+            }),
+        }
+    }
+}
+
+// An instructor's availability for every day, hour and mode, looked up from their
+// application once so building the matrix doesn't re-parse the same talloc strings
+struct AvailabilityLookup {
+    slots: [[[Option<Availability>; 2]; 24]; 5],
+}
+
+impl AvailabilityLookup {
+    fn from_application(application: &TallocApplication) -> Self {
+        let mut slots = [[[None; 2]; 24]; 5];
+        for day in [Day::Mon, Day::Tue, Day::Wed, Day::Thu, Day::Fri] {
+            for hour in 0..24 {
+                let time = TimeOfDay::from_hour(hour).expect("hour should be valid");
+                for mode in [Mode::F2F, Mode::Online] {
+                    slots[day as usize][hour as usize][mode as usize] =
+                        application.get_availability(day, time, mode);
+                }
+            }
+        }
+        AvailabilityLookup { slots }
+    }
+
+    fn get(&self, day: Day, time: TimeOfDay, mode: Mode) -> Option<Availability> {
+        self.slots[day as usize][time.as_24_hours() as usize][mode as usize]
+    }
+
+    // The availability for each hour of the session
+    fn hour_availabilities(&self, session: &Session) -> Option<Vec<Availability>> {
+        (0..session.duration.hours())
+            .map(|hour_offset| {
+                self.get(
+                    session.day,
+                    session.start_time.add_hr(hour_offset)?,
+                    session.mode,
+                )
+            })
+            .collect()
+    }
 }
 
 impl AvailabilityMatrix {
@@ -31,40 +102,99 @@ impl AvailabilityMatrix {
         instructors: &[Instructor],
         sessions: &[Session],
         applications: &TallocApps,
+        hour_policy: HourCombinePolicy,
     ) -> Result<AvailabilityMatrix> {
-        let mut availability_session_x_instructor =
-            Vec::with_capacity(instructors.len() * sessions.len());
-
-        for session in sessions.iter() {
-            for instructor in instructors.iter() {
+        let lookups = instructors
+            .iter()
+            .map(|instructor| {
                 let application =
                     applications
                         .get_application(&instructor.zid)
                         .with_context(|| {
                             format!("{} does not have a talloc application!", instructor.zid)
                         })?;
+                Ok(AvailabilityLookup::from_application(&application))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-                availability_session_x_instructor.push(
-                    check_availability(application, session).with_context(|| {
-                        anyhow!(
-                            "failed to lookup {}'s availability for {}",
-                            instructor.zid,
-                            session.class_name
-                        )
-                    })?,
-                );
+        let mut availability_session_x_instructor =
+            Vec::with_capacity(instructors.len() * sessions.len());
+        let mut partial_availabilities = Vec::new();
+
+        for session in sessions.iter() {
+            for (instructor, lookup) in instructors.iter().zip(&lookups) {
+                let hours = lookup
+                    .hour_availabilities(session)
+                    .and_then(|hours| Some((hour_policy.combine(&hours)?, hours)));
+                let (availability, hours) = hours.with_context(|| {
+                    anyhow!(
+                        "failed to lookup {}'s availability for {}",
+                        instructor.zid,
+                        session.class_name
+                    )
+                })?;
+
+                let possible_hours = hours
+                    .iter()
+                    .map(|&hour| hour != Availability::Impossible)
+                    .collect::<Vec<_>>();
+                if possible_hours.contains(&true) && possible_hours.contains(&false) {
+                    partial_availabilities.push((
+                        session.session_id,
+                        instructor.instructor_id,
+                        possible_hours,
+                    ));
+                }
+
+                availability_session_x_instructor.push(availability);
             }
         }
 
         Ok(AvailabilityMatrix {
             num_instructors: instructors.len(),
             availability_session_x_instructor,
+            partial_availabilities,
         })
     }
 
+    pub fn from_fn(
+        instructors: &[Instructor],
+        sessions: &[Session],
+        mut availability: impl FnMut(&Session, &Instructor) -> Availability,
+    ) -> AvailabilityMatrix {
+        let mut availability_session_x_instructor =
+            Vec::with_capacity(instructors.len() * sessions.len());
+
+        for session in sessions.iter() {
+            for instructor in instructors.iter() {
+                availability_session_x_instructor.push(availability(session, instructor));
+            }
+        }
+
+        AvailabilityMatrix {
+            num_instructors: instructors.len(),
+            availability_session_x_instructor,
+            partial_availabilities: Vec::new(),
+        }
+    }
+
+    fn get_index(&self, session: SessionId, instructor: InstructorId) -> usize {
+        let num_sessions = self
+            .availability_session_x_instructor
+            .len()
+            .checked_div(self.num_instructors)
+            .unwrap_or(0);
+        assert!(
+            session.raw_index() < num_sessions && instructor.raw_index() < self.num_instructors,
+            "availability lookup for {session:?} and {instructor:?} is outside of the {num_sessions} sessions x {} instructors matrix",
+            self.num_instructors
+        );
+
+        session.raw_index() * self.num_instructors + instructor.raw_index()
+    }
+
     pub fn get_availability(&self, session: SessionId, instructor: InstructorId) -> Availability {
-        self.availability_session_x_instructor
-            [session.raw_index() * self.num_instructors + instructor.raw_index()]
+        self.availability_session_x_instructor[self.get_index(session, instructor)]
     }
 
     pub fn set_availability(
@@ -73,8 +203,36 @@ impl AvailabilityMatrix {
         instructor: InstructorId,
         updated: Availability,
     ) {
-        self.availability_session_x_instructor
-            [session.raw_index() * self.num_instructors + instructor.raw_index()] = updated;
+        let index = self.get_index(session, instructor);
+        self.availability_session_x_instructor[index] = updated;
+    }
+
+    // The instructors who could take only some hours of a session which is impossible
+    // for them (ignoring any which overrides have since made possible), with whether
+    // each hour is possible
+    pub fn partial_availabilities(
+        &self,
+    ) -> impl Iterator<Item = (SessionId, InstructorId, &[bool])> + '_ {
+        self.partial_availabilities
+            .iter()
+            .filter(|(session, instructor, _)| {
+                self.get_availability(*session, *instructor) == Availability::Impossible
+            })
+            .map(|(session, instructor, hours)| (*session, *instructor, hours.as_slice()))
+    }
+
+    // How many instructors could possibly take the session
+    pub fn num_feasible_instructors(
+        &self,
+        session: SessionId,
+        instructors: &[Instructor],
+    ) -> usize {
+        instructors
+            .iter()
+            .filter(|instructor| {
+                self.get_availability(session, instructor.instructor_id) != Availability::Impossible
+            })
+            .count()
     }
 
     pub fn make_availability_report(
@@ -123,3 +281,96 @@ impl AvailabilityMatrix {
         report
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{classes::Class, session::classes_to_sessions, tsv::Tsv};
+
+    #[test]
+    fn precomputed_lookup_matches_applications() -> Result<()> {
+        let mut rng = fastrand::Rng::with_seed(0);
+        let days = [Day::Mon, Day::Tue, Day::Wed, Day::Thu, Day::Fri];
+
+        let instructors_tsv = Tsv::try_from_str(
+            "instructors.tsv",
+            "name\tzid\tminT\tmaxT\tminA\tmaxA\n\
+             A\tz1\t0\t1\t0\t1\n\
+             B\tz2\t0\t1\t0\t1\n\
+             C\tz3\t0\t1\t0\t1\n",
+        )?;
+        let instructors = Instructor::vec_from_tsvs(&[instructors_tsv], false)?;
+
+        // random face to face and online levels for every slot of every instructor
+        let applications = instructors
+            .iter()
+            .map(|instructor| {
+                let slots = days
+                    .iter()
+                    .flat_map(|day| (0..24).map(move |hour| (*day, hour)))
+                    .map(|(day, hour)| {
+                        (
+                            format!("{}{hour:02}", day.short_lowercase()),
+                            serde_json::Value::String(rng.u8(0..16).to_string()),
+                        )
+                    })
+                    .collect();
+                (instructor.zid.clone(), serde_json::Value::Object(slots))
+            })
+            .collect::<HashMap<_, _>>();
+        let applications = TallocApps::from_applications(applications);
+
+        let classes = (0..40)
+            .map(|idx| Class {
+                name: format!("C{idx:02}"),
+                day: days[rng.usize(0..days.len())],
+                start: TimeOfDay::from_hour(rng.u8(6..=20)).expect("hour should be valid"),
+                mode: if rng.bool() { Mode::Online } else { Mode::F2F },
+                location: String::new(),
+                lab_location: String::new(),
+                ignore_tut: false,
+                ignore_lab: false,
+                continuity_group: None,
+                alternative_days: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        let sessions = classes_to_sessions(&classes)?;
+
+        for policy in [
+            HourCombinePolicy::Min,
+            HourCombinePolicy::Average,
+            HourCombinePolicy::Majority,
+        ] {
+            let matrix = AvailabilityMatrix::build(&instructors, &sessions, &applications, policy)?;
+
+            for session in &sessions {
+                for instructor in &instructors {
+                    let application = applications.get_application(&instructor.zid).unwrap();
+                    let hours = (0..session.duration.hours())
+                        .map(|hour_offset| {
+                            application
+                                .get_availability(
+                                    session.day,
+                                    session.start_time.add_hr(hour_offset).unwrap(),
+                                    session.mode,
+                                )
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>();
+
+                    assert_eq!(
+                        matrix.get_availability(session.session_id, instructor.instructor_id),
+                        policy.combine(&hours).unwrap(),
+                        "{policy:?} availability of {} for {}",
+                        instructor.zid,
+                        session.short_description()
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use std::{fs, path::Path, time::Instant};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    availabilities::AvailabilityMatrix,
+    class_prefs::ClassPreferences,
+    classes::{Class, Mode},
+    costs::{CostConfig, CostValue},
+    enrolments::Enrolments,
+    evaluator::{Problem, Solution},
+    history::TeachingHistory,
+    instructor::{ClassTypeRequirement, Instructor, InstructorId},
+    session::{
+        classes_to_sessions, ConcurrentGroups, ContinuityGroups, DayVariants, LabLeads,
+        OverlapLevels, OverlapMatrix, OverlapRequirement, Session, SessionId,
+    },
+    solver::{solve_once, SolverOptions, SolverSeed},
+    talloc::Availability,
+    utils::{indent_lines, Day, TimeOfDay},
+};
+
+const BENCHMARK_PROBLEM_SEED: u64 = 0;
+const BENCHMARK_NUM_CLASSES: usize = 60;
+const BENCHMARK_NUM_INSTRUCTORS: usize = 20;
+const BENCHMARK_SEEDS: [u64; 3] = [1, 2, 3];
+const BENCHMARK_ROUNDS: u64 = 2_000_000;
+// how much slower than the baseline a run can be before it counts as a regression
+const BENCHMARK_TIME_SLACK: f32 = 1.5;
+
+// A randomly generated problem which doesn't need any input files
+pub struct SyntheticProblem {
+    sessions: Vec<Session>,
+    instructors: Vec<Instructor>,
+    availabilities: AvailabilityMatrix,
+    overlaps_sharp: OverlapMatrix,
+    overlap_levels: OverlapLevels,
+    lab_leads: LabLeads,
+    contiguous_groups: Vec<Vec<SessionId>>,
+    continuity_groups: ContinuityGroups,
+    concurrent_groups: ConcurrentGroups,
+    day_variants: DayVariants,
+    cost_config: CostConfig,
+    initial_solution: Solution,
+    mismatch_weights: Vec<u32>,
+    unlocked_sessions: Vec<SessionId>,
+    class_preferences: ClassPreferences,
+    enrolments: Enrolments,
+    history: TeachingHistory,
+}
+
+impl SyntheticProblem {
+    pub fn generate(seed: u64, num_classes: usize, num_instructors: usize) -> Result<Self> {
+        let mut rng = fastrand::Rng::with_seed(seed);
+
+        let days = [Day::Mon, Day::Tue, Day::Wed, Day::Thu, Day::Fri];
+        let classes = (0..num_classes)
+            .map(|idx| Class {
+                name: format!("C{idx:03}"),
+                day: days[rng.usize(0..days.len())],
+                start: TimeOfDay::from_hour(rng.u8(9..=18)).expect("hour should be valid"),
+                mode: if rng.u8(0..4) == 0 {
+                    Mode::Online
+                } else {
+                    Mode::F2F
+                },
+                location: String::new(),
+                lab_location: String::new(),
+                ignore_tut: false,
+                ignore_lab: false,
+                continuity_group: None,
+                alternative_days: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        let sessions = classes_to_sessions(&classes)?;
+
+        let instructors = (0..num_instructors)
+            .map(|idx| {
+                let (min_tutes, max_tutes) = (rng.u8(0..=1), rng.u8(2..=4));
+                let (min_lab_assists, max_lab_assists) = (rng.u8(0..=1), rng.u8(2..=4));
+                Instructor {
+                    instructor_id: InstructorId::from_index(idx),
+                    name: format!("Instructor {idx}"),
+                    zid: format!("z{:07}", 5000000 + idx),
+                    class_type_requirement: ClassTypeRequirement {
+                        min_tutes,
+                        max_tutes,
+                        min_lab_assists,
+                        max_lab_assists,
+                        min_total_classes: min_tutes + min_lab_assists,
+                        max_total_classes: max_tutes + max_lab_assists,
+                        min_hours: 0,
+                        max_hours: u8::MAX,
+                        max_start_times: u8::MAX,
+                        max_per_day: u8::MAX,
+                        pinned_count: None,
+                    },
+                    seniority: None,
+                    is_reserve: false,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let availabilities =
+            AvailabilityMatrix::from_fn(&instructors, &sessions, |_, _| match rng.u8(0..10) {
+                0..=2 => Availability::Impossible,
+                3 => Availability::Dislike,
+                4..=7 => Availability::Possible,
+                _ => Availability::Preferred,
+            });
+
+        let overlaps_sharp = OverlapMatrix::from_sessions(&sessions, OverlapRequirement::Sharp);
+        let overlap_levels = OverlapLevels::from_matrices(
+            &sessions,
+            &overlaps_sharp,
+            &OverlapMatrix::from_sessions(&sessions, OverlapRequirement::WithPadding),
+            &OverlapMatrix::from_sessions(&sessions, OverlapRequirement::SameDay),
+        );
+
+        let cost_config: CostConfig = toml::from_str(include_str!("../costs.example.toml"))
+            .context("failed to parse bundled example costs")?;
+
+        Ok(SyntheticProblem {
+            initial_solution: Solution::empty(sessions.len(), false),
+            mismatch_weights: vec![1; sessions.len()],
+            unlocked_sessions: sessions.iter().map(|session| session.session_id).collect(),
+            lab_leads: LabLeads::from_sessions(&sessions),
+            contiguous_groups: cost_config.contiguous_group_sessions(&sessions),
+            continuity_groups: ContinuityGroups::default(),
+            concurrent_groups: ConcurrentGroups::from_sessions(&sessions, &overlaps_sharp),
+            day_variants: DayVariants::default(),
+            class_preferences: ClassPreferences::default(),
+            enrolments: Enrolments::default(),
+            history: TeachingHistory::default(),
+            sessions,
+            instructors,
+            availabilities,
+            overlaps_sharp,
+            overlap_levels,
+            cost_config,
+        })
+    }
+
+    pub fn as_problem(&self) -> Problem<'_> {
+        Problem {
+            sessions: &self.sessions,
+            instructors: &self.instructors,
+            availabilities: &self.availabilities,
+            overlap_sharp: &self.overlaps_sharp,
+            overlap_levels: &self.overlap_levels,
+            lab_leads: &self.lab_leads,
+            contiguous_groups: &self.contiguous_groups,
+            continuity_groups: &self.continuity_groups,
+            concurrent_groups: &self.concurrent_groups,
+            day_variants: &self.day_variants,
+            cost_config: &self.cost_config,
+            initial_solution: &self.initial_solution,
+            mismatch_weights: &self.mismatch_weights,
+            unlocked_sessions: &self.unlocked_sessions,
+            allow_unassigned: true,
+            focus_instructor: None,
+            class_preferences: &self.class_preferences,
+            enrolments: &self.enrolments,
+            history: &self.history,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BenchmarkResults {
+    final_costs: Vec<Option<CostValue>>,
+    seconds: f32,
+}
+
+// Solves a fixed synthetic problem with fixed seeds, and compares against the
+// results recorded in `baseline_path` (recording them if there aren't any yet)
+pub fn run_benchmark(baseline_path: &Path) -> Result<()> {
+    let synthetic = SyntheticProblem::generate(
+        BENCHMARK_PROBLEM_SEED,
+        BENCHMARK_NUM_CLASSES,
+        BENCHMARK_NUM_INSTRUCTORS,
+    )?;
+    let problem = synthetic.as_problem();
+
+    let start_time = Instant::now();
+    let final_costs = BENCHMARK_SEEDS
+        .iter()
+        .map(|&rng_seed| {
+            let output = solve_once(
+                problem,
+                problem.initial_solution,
+                SolverSeed {
+                    num_rounds: BENCHMARK_ROUNDS,
+                    rng_seed,
+                },
+                SolverOptions::default(),
+            );
+            println!("Benchmark seed {rng_seed}: cost {:?}", output.final_cost);
+            output.final_cost
+        })
+        .collect::<Vec<_>>();
+    let seconds = start_time.elapsed().as_secs_f32();
+    println!("Benchmark took {seconds:.3} seconds");
+
+    let results = BenchmarkResults {
+        final_costs,
+        seconds,
+    };
+
+    if !baseline_path.exists() {
+        fs::write(baseline_path, serde_json::to_string_pretty(&results)?)
+            .with_context(|| anyhow!("failed to write {}", baseline_path.display()))?;
+        println!("Recorded benchmark baseline in {}", baseline_path.display());
+        return Ok(());
+    }
+
+    let baseline: BenchmarkResults = serde_json::from_str(
+        &fs::read_to_string(baseline_path)
+            .with_context(|| anyhow!("failed to read {}", baseline_path.display()))?,
+    )
+    .with_context(|| anyhow!("failed to parse {}", baseline_path.display()))?;
+
+    let mut regressions = Vec::new();
+    for ((seed, new_cost), old_cost) in BENCHMARK_SEEDS
+        .iter()
+        .zip(&results.final_costs)
+        .zip(&baseline.final_costs)
+    {
+        let is_regression = match (new_cost, old_cost) {
+            (None, Some(_)) => true,
+            (Some(new_cost), Some(old_cost)) => new_cost > old_cost,
+            (_, None) => false,
+        };
+        if is_regression {
+            regressions.push(format!(
+                "seed {seed} got cost {new_cost:?} but the baseline is {old_cost:?}"
+            ));
+        }
+    }
+    if results.seconds > baseline.seconds * BENCHMARK_TIME_SLACK {
+        regressions.push(format!(
+            "took {:.3} seconds but the baseline is {:.3} seconds",
+            results.seconds, baseline.seconds
+        ));
+    }
+
+    if !regressions.is_empty() {
+        bail!(
+            "benchmark regressed compared to {}:\n{}",
+            baseline_path.display(),
+            indent_lines(&regressions.join("\n"), 4)
+        );
+    }
+
+    println!("Benchmark is within the baseline");
+    Ok(())
+}
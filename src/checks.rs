@@ -1,8 +1,13 @@
+use std::collections::HashSet;
+
 use crate::{
+    classes::Class,
     costs::Constraint,
     evaluator::Problem,
-    instructor::{ClassTypeRequirement, Instructor},
-    session::SessionType,
+    instructor::{ClassTypeRequirement, Instructor, InstructorId},
+    session::{Session, SessionType},
+    talloc::Availability,
+    utils::Day,
 };
 
 #[allow(non_snake_case)]
@@ -16,6 +21,8 @@ fn check_instructor_class_reqs(instructor: &Instructor) {
     let maxA = instructor.class_type_requirement.max_lab_assists;
     let minC = instructor.class_type_requirement.min_total_classes;
     let maxC = instructor.class_type_requirement.max_total_classes;
+    let min_hours = instructor.class_type_requirement.min_hours;
+    let max_hours = instructor.class_type_requirement.max_hours;
 
     macro_rules! check_constraint {
         ($cond:expr) => {
@@ -36,25 +43,198 @@ fn check_instructor_class_reqs(instructor: &Instructor) {
 
     check_constraint!(minT + minA <= minC);
     check_constraint!(maxC <= maxA + maxT);
+
+    check_constraint!(min_hours <= max_hours);
+}
+
+// Check that the instructor is actually available for enough sessions to meet their minimums
+fn check_instructor_feasible_sessions(
+    problem: Problem,
+    instructor: &Instructor,
+    has_default_availability: bool,
+) {
+    let feasible_sessions = problem
+        .sessions
+        .iter()
+        .filter(|session| {
+            problem
+                .availabilities
+                .get_availability(session.session_id, instructor.instructor_id)
+                != Availability::Impossible
+        })
+        .collect::<Vec<_>>();
+
+    // this also happens with the default application for missing talloc applications,
+    // but is more often someone filling out the form wrong
+    if feasible_sessions.is_empty() && !problem.sessions.is_empty() {
+        // the default application was already reported when it was used
+        if has_default_availability {
+            return;
+        }
+        println!(
+            "Warning! {} ({}) is impossible for every session so will never be assigned anything, check their availability",
+            instructor.zid, instructor.name
+        );
+        return;
+    }
+
+    let feasible_tuts = feasible_sessions
+        .iter()
+        .filter(|session| matches!(session.typ, SessionType::TutLab))
+        .count();
+    let feasible_labs = feasible_sessions.len() - feasible_tuts;
+
+    let requirement = &instructor.class_type_requirement;
+    if let Some(pinned_count) = requirement.pinned_count {
+        let max_achievable = feasible_tuts.min(requirement.max_tutes as usize)
+            + feasible_labs.min(requirement.max_lab_assists as usize);
+        if max_achievable < pinned_count as usize {
+            println!(
+                "Warning! {} ({}) has a pinned count of {pinned_count} classes but their availability and maxT/maxA only allow {max_achievable}",
+                instructor.zid, instructor.name
+            );
+        }
+    }
+
+    for (description, feasible, min) in [
+        ("tut+labs", feasible_tuts, requirement.min_tutes),
+        ("lab assists", feasible_labs, requirement.min_lab_assists),
+        (
+            "classes",
+            feasible_sessions.len(),
+            requirement.min_total_classes,
+        ),
+    ] {
+        if feasible < min as usize {
+            println!(
+                "Warning! {} ({}) is only available for {feasible} {description} but has a minimum of {min}",
+                instructor.zid, instructor.name
+            );
+        }
+    }
+}
+
+// Initial assignments which are now impossible, often because an override changed
+// the availability after the initial solution was written
+fn check_initial_assignments_possible(problem: Problem) {
+    for session in problem.sessions {
+        let Some(instructor_id) =
+            problem.initial_solution.assignment[session.session_id.raw_index()]
+        else {
+            continue;
+        };
+        if problem
+            .availabilities
+            .get_availability(session.session_id, instructor_id)
+            == Availability::Impossible
+        {
+            let instructor = &problem.instructors[instructor_id.raw_index()];
+            println!(
+                "Warning! The initial solution assigns {} ({}) to {} but they are impossible for it (check overrides*.tsv)",
+                instructor.zid,
+                instructor.name,
+                session.short_description()
+            );
+        }
+    }
+}
+
+// A macro level summary of each day: how many sessions there are against how
+// many of them the available instructors could take between them, counting each
+// instructor at most up to their daily and total limits
+fn check_day_feasibility(problem: Problem, active_sessions: &[&Session]) {
+    let mut summary = String::new();
+    let mut num_sessions_by_day = [0; 5];
+    let mut capacity_by_day = [0; 5];
+    let mut num_instructors_by_day = [0; 5];
+
+    for session in active_sessions {
+        num_sessions_by_day[session.day as usize] += 1;
+    }
+
+    for instructor in problem.instructors {
+        let mut feasible_by_day = [0; 5];
+        for session in active_sessions {
+            if problem
+                .availabilities
+                .get_availability(session.session_id, instructor.instructor_id)
+                != Availability::Impossible
+            {
+                feasible_by_day[session.day as usize] += 1;
+            }
+        }
+
+        let requirement = &instructor.class_type_requirement;
+        let daily_limit = requirement.max_per_day.min(requirement.max_total_classes) as usize;
+        for (day, &feasible) in feasible_by_day.iter().enumerate() {
+            if feasible > 0 {
+                num_instructors_by_day[day] += 1;
+                capacity_by_day[day] += feasible.min(daily_limit);
+            }
+        }
+    }
+
+    for day in [Day::Mon, Day::Tue, Day::Wed, Day::Thu, Day::Fri] {
+        let num_sessions = num_sessions_by_day[day as usize];
+        if num_sessions == 0 {
+            continue;
+        }
+        let capacity = capacity_by_day[day as usize];
+        summary += &format!(
+            "    {day}: {num_sessions} sessions, {} available instructors who could take {capacity} of them{}\n",
+            num_instructors_by_day[day as usize],
+            if capacity < num_sessions {
+                " (Warning! Not enough availability)"
+            } else {
+                ""
+            }
+        );
+    }
+
+    print!("Per day feasibility:\n{summary}");
 }
 
 #[allow(non_snake_case)]
-pub fn check_problem(problem: Problem) {
+pub fn check_problem(
+    problem: Problem,
+    classes: &[Class],
+    defaulted_instructors: &HashSet<InstructorId>,
+) {
     for instructor in problem.instructors {
         check_instructor_class_reqs(instructor);
+        check_instructor_feasible_sessions(
+            problem,
+            instructor,
+            defaulted_instructors.contains(&instructor.instructor_id),
+        );
     }
+    check_initial_assignments_possible(problem);
 
-    let total_actual_tuts = problem
+    // classes with alternative days are only counted on the day the initial solution uses
+    let inactive_sessions = problem
+        .day_variants
+        .inactive_sessions(&problem.initial_solution.assignment);
+    let active_sessions = problem
         .sessions
+        .iter()
+        .filter(|session| !inactive_sessions[session.session_id.raw_index()])
+        .collect::<Vec<_>>();
+    check_day_feasibility(problem, &active_sessions);
+
+    let total_actual_tuts = active_sessions
         .iter()
         .filter(|session| matches!(session.typ, SessionType::TutLab))
         .count();
-    let total_actual_labs = problem
-        .sessions
+    let total_actual_labs = active_sessions
         .iter()
         .filter(|session| matches!(session.typ, SessionType::LabAssist))
         .count();
-    let total_actual_classes = problem.sessions.len();
+    let total_actual_classes = active_sessions.len();
+
+    // sessions dropped by the `ignore tut`/`ignore lab` columns of classes.tsv
+    let num_ignored_tuts = classes.iter().filter(|class| class.ignore_tut).count();
+    let num_ignored_labs = classes.iter().filter(|class| class.ignore_lab).count();
+    let num_ignored_classes = num_ignored_tuts + num_ignored_labs;
 
     let sum_requirement = |f: fn(&ClassTypeRequirement) -> u8| {
         problem
@@ -72,14 +252,15 @@ pub fn check_problem(problem: Problem) {
     let sum_maxC = sum_requirement(|r| r.max_total_classes);
 
     macro_rules! check_constraint {
-        ($a:ident $comparison:tt $b:ident, $resolution:expr) => {
+        ($a:ident $comparison:tt $b:ident, $ignored:ident, $resolution:expr) => {
             if !($a $comparison $b) {
                 println!(
-                    "Warning! Condition `{}` violated: you probably want to {}\nNote {} = {} and {} = {}",
+                    "Warning! Condition `{}` violated: you probably want to {}\nNote {} = {} and {} = {} ({} sessions were dropped by ignore flags in classes.tsv)",
                     stringify!($a $comparison $b),
                     $resolution,
                     stringify!($a), $a,
                     stringify!($b), $b,
+                    $ignored,
                 );
             }
         };
@@ -87,28 +268,34 @@ pub fn check_problem(problem: Problem) {
 
     check_constraint!(
         sum_minT <= total_actual_tuts,
+        num_ignored_tuts,
         "decrease some of the instructor's minT values"
     );
     check_constraint!(
         total_actual_tuts <= sum_maxT,
+        num_ignored_tuts,
         "increase some of the instructor's maxT values or add more instructors"
     );
 
     check_constraint!(
         sum_minA <= total_actual_labs,
+        num_ignored_labs,
         "decrease some of the instructor's minA values"
     );
     check_constraint!(
         total_actual_labs <= sum_maxA,
+        num_ignored_labs,
         "increase some of the instructor's minA values or add more instructors"
     );
 
     check_constraint!(
         sum_minC <= total_actual_classes,
+        num_ignored_classes,
         "decrease some of the instructor's minC values"
     );
     check_constraint!(
         total_actual_classes <= sum_maxC,
+        num_ignored_classes,
         "increase some of the instructor's maxC values or add more instructors"
     );
 
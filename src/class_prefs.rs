@@ -0,0 +1,71 @@
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::{
+    instructor::{Instructor, InstructorId},
+    session::{Session, SessionId, SessionType},
+    tsv::Tsv,
+    utils::matches_glob_list,
+};
+
+// A preference from the course team that one of `instructors` takes
+// at least one of `sessions`
+pub struct ClassPreference {
+    pub sessions: Vec<SessionId>,
+    pub instructors: Vec<InstructorId>,
+}
+
+#[derive(Default)]
+pub struct ClassPreferences {
+    pub preferences: Vec<ClassPreference>,
+}
+
+impl ClassPreferences {
+    pub fn from_tsv(tsv: &Tsv, instructors: &[Instructor], sessions: &[Session]) -> Result<Self> {
+        let mut preferences = Vec::new();
+
+        for row in tsv {
+            let class_name = row.get("class")?;
+            let class_type = row.get("type")?;
+            let zids = row.get("zid")?;
+
+            let matching_sessions = sessions
+                .iter()
+                .filter(|session| {
+                    let this_session_type_name = match session.typ {
+                        SessionType::TutLab => "tut",
+                        SessionType::LabAssist => "lab",
+                    };
+                    matches_glob_list(class_name, &session.class_name)
+                        && matches_glob_list(class_type, this_session_type_name)
+                })
+                .map(|session| session.session_id)
+                .collect::<Vec<_>>();
+
+            if matching_sessions.is_empty() {
+                bail!("class preference for {class_name} {class_type} doesn't match any sessions");
+            }
+
+            let preferred_instructors = zids
+                .split(',')
+                .map(|zid| {
+                    instructors
+                        .iter()
+                        .find(|instructor| instructor.zid == zid.trim())
+                        .map(|instructor| instructor.instructor_id)
+                        .with_context(|| {
+                            anyhow!(
+                                "cannot find instructor {zid} for class preference {class_name}"
+                            )
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            preferences.push(ClassPreference {
+                sessions: matching_sessions,
+                instructors: preferred_instructors,
+            });
+        }
+
+        Ok(ClassPreferences { preferences })
+    }
+}
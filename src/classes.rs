@@ -15,9 +15,18 @@ pub struct Class {
     pub day: Day,
     pub start: TimeOfDay,
     pub mode: Mode,
+    // where the tutorial and lab are held, like "Quadrangle G040", or "Online"
+    pub location: String,
+    pub lab_location: String,
 
     pub ignore_tut: bool,
     pub ignore_lab: bool,
+
+    // classes with the same (optional) group label should keep the same instructor
+    pub continuity_group: Option<String>,
+
+    // other days the class could run on instead, at the same time, if that staffs better
+    pub alternative_days: Vec<Day>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -26,17 +35,23 @@ pub enum Mode {
     Online,
 }
 
-fn extract_meeting(meeting: &str) -> Option<(Day, TimeOfDay, TimeOfDay, Mode)> {
+fn extract_meeting(
+    meeting: &str,
+    start_time_grid: Option<u16>,
+) -> Option<(Day, TimeOfDay, TimeOfDay, Mode, &str)> {
     let (before_paren, after_paren) = meeting.split_once(" (")?;
     let (day, time) = before_paren.split_once(' ')?;
     let (_weeks, location) = after_paren.strip_suffix(')')?.split_once(", ")?;
 
     let (start, end) = if time.contains('-') {
         let (star_raw, end_raw) = time.split_once('-')?;
-        (star_raw.parse().ok()?, end_raw.parse().ok()?)
+        (
+            TimeOfDay::parse_snapped(star_raw, start_time_grid)?,
+            TimeOfDay::parse_snapped(end_raw, start_time_grid)?,
+        )
     } else {
-        let start: TimeOfDay = time.parse().ok()?;
-        (start, start.add_hr(1))
+        let start = TimeOfDay::parse_snapped(time, start_time_grid)?;
+        (start, start.add_hr(1)?)
     };
 
     Some((
@@ -48,40 +63,91 @@ fn extract_meeting(meeting: &str) -> Option<(Day, TimeOfDay, TimeOfDay, Mode)> {
         } else {
             Mode::F2F
         },
+        location,
     ))
 }
 
-fn extract_and_check_meetings(times: &str) -> Result<(Day, TimeOfDay, Mode)> {
-    let (tut_meeting, lab_meeting) = times
-        .split("; ")
-        .collect_tuple()
-        .ok_or_else(|| anyhow!("class time {times:?} doesn't have two meetings"))?;
+// Usually there are exactly two meetings (the tut then the lab), but exports sometimes
+// include others (like a lecture). In that case the tut+lab is the unique pair of
+// meetings which fits the expected pattern, and the rest are ignored.
+fn extract_and_check_meetings(
+    times: &str,
+    start_time_grid: Option<u16>,
+) -> Result<(Day, TimeOfDay, Mode, &str, &str)> {
+    let meetings = times.split("; ").collect::<Vec<_>>();
+
+    match meetings[..] {
+        [] | [_] => bail!("class time {times:?} doesn't have two meetings"),
+        [tut_meeting, lab_meeting] => {
+            check_tut_lab_meetings(tut_meeting, lab_meeting, start_time_grid)
+        }
+        _ => {
+            let candidates = meetings
+                .iter()
+                .tuple_combinations()
+                .flat_map(|(&a, &b)| [(a, b), (b, a)])
+                .filter_map(|(tut_meeting, lab_meeting)| {
+                    check_tut_lab_meetings(tut_meeting, lab_meeting, start_time_grid).ok()
+                })
+                .collect::<Vec<_>>();
+            match candidates[..] {
+                [found] => Ok(found),
+                [] => bail!("couldn't find a tut followed by a lab in the meetings {times:?}"),
+                _ => bail!("found more than one possible tut+lab in the meetings {times:?}"),
+            }
+        }
+    }
+}
 
-    let (tut_day, tut_start, tut_end, tut_mode) = extract_meeting(tut_meeting)
-        .ok_or_else(|| anyhow!("bad tutorial meeting {tut_meeting:?}"))?;
+// Gives the tut's day, start time, mode and location, and the lab's location
+fn check_tut_lab_meetings<'a>(
+    tut_meeting: &'a str,
+    lab_meeting: &'a str,
+    start_time_grid: Option<u16>,
+) -> Result<(Day, TimeOfDay, Mode, &'a str, &'a str)> {
+    let (tut_day, tut_start, tut_end, tut_mode, tut_location) =
+        extract_meeting(tut_meeting, start_time_grid)
+            .ok_or_else(|| anyhow!("bad tutorial meeting {tut_meeting:?}"))?;
 
-    let (lab_day, lab_start, lab_end, lab_mode) =
-        extract_meeting(lab_meeting).ok_or_else(|| anyhow!("bad lab meeting {lab_meeting:?}"))?;
+    let (lab_day, lab_start, lab_end, lab_mode, lab_location) =
+        extract_meeting(lab_meeting, start_time_grid)
+            .ok_or_else(|| anyhow!("bad lab meeting {lab_meeting:?}"))?;
 
     if tut_day != lab_day {
         bail!("mismatch between tut and lab days");
-    } else if tut_start.add_hr(TUT_DURATION_HOURS) != tut_end {
+    } else if tut_start.add_hr(TUT_DURATION_HOURS) != Some(tut_end) {
         bail!("tut is the wrong length");
     } else if tut_end != lab_start {
         bail!("lab is not immediately after tut");
-    } else if lab_start.add_hr(LAB_DURATION_HOURS) != lab_end {
+    } else if lab_start.add_hr(LAB_DURATION_HOURS) != Some(lab_end) {
         bail!("lab is the wrong length");
     } else if lab_mode != tut_mode {
         bail!("tut and lab mode disagree");
     } else {
-        Ok((tut_day, tut_start, tut_mode))
+        Ok((tut_day, tut_start, tut_mode, tut_location, lab_location))
     }
 }
 
-impl<'a> TryFrom<TsvRow<'a>> for Class {
-    type Error = anyhow::Error;
+// A comma separated list of days, ignoring the day the class is already on
+fn parse_alternative_days(days: &str, timetabled_day: Day) -> Result<Vec<Day>> {
+    let mut alternative_days = Vec::new();
+    for alternative in days.split(',').map(str::trim) {
+        if alternative.is_empty() {
+            continue;
+        }
+        let alternative = alternative
+            .parse::<Day>()
+            .map_err(|_| anyhow!("{alternative:?} is not a day"))?;
+        if alternative != timetabled_day && !alternative_days.contains(&alternative) {
+            alternative_days.push(alternative);
+        }
+    }
+    Ok(alternative_days)
+}
 
-    fn try_from(row: TsvRow<'a>) -> Result<Self> {
+impl Class {
+    // `start_time_grid` is in minutes, see `TimeOfDay::parse_snapped`
+    fn from_row(row: TsvRow, start_time_grid: Option<u16>) -> Result<Self> {
         let name = String::from(row.get("section")?.trim());
 
         let class_type = row.get("type")?.trim();
@@ -94,8 +160,9 @@ impl<'a> TryFrom<TsvRow<'a>> for Class {
             bail!("bad class status {status:?} for {name}, either manually change to \"Open\" or remove it");
         }
 
-        let (day, start, mode) = extract_and_check_meetings(row.get("times")?.trim())
-            .with_context(|| format!("error while extracting meeting info for {name}"))?;
+        let (day, start, mode, location, lab_location) =
+            extract_and_check_meetings(row.get("times")?.trim(), start_time_grid)
+                .with_context(|| format!("error while extracting meeting info for {name}"))?;
 
         let get_ignore = |field_name: &str| {
             Ok(match row.get(field_name) {
@@ -110,19 +177,79 @@ impl<'a> TryFrom<TsvRow<'a>> for Class {
             })
         };
 
+        let alternative_days = match row.get("alternative_days") {
+            Ok(days) => parse_alternative_days(days, day)
+                .with_context(|| format!("bad alternative_days for {name}"))?,
+            Err(_) => Vec::new(),
+        };
+
         Ok(Class {
             name,
             day,
             start,
             mode,
+            location: location.trim().into(),
+            lab_location: lab_location.trim().into(),
             ignore_tut: get_ignore("ignore tut")?,
             ignore_lab: get_ignore("ignore lab")?,
+            continuity_group: row
+                .get("continuity_group")
+                .ok()
+                .map(str::trim)
+                .filter(|group| !group.is_empty())
+                .map(String::from),
+            alternative_days,
         })
     }
-}
 
-impl Class {
-    pub fn vec_from_tsv(tsv: &Tsv) -> Result<Vec<Class>> {
-        tsv.into_iter().map(Class::try_from).collect()
+    // With `skip_bad_classes` any rows which can't be parsed are reported and left
+    // out rather than failing the whole load
+    pub fn vec_from_tsv(
+        tsv: &Tsv,
+        start_time_grid: Option<u16>,
+        skip_bad_classes: bool,
+    ) -> Result<Vec<Class>> {
+        if !skip_bad_classes {
+            return tsv
+                .into_iter()
+                .map(|row| Class::from_row(row, start_time_grid))
+                .collect();
+        }
+
+        let mut classes = Vec::new();
+        let mut num_skipped = 0;
+        for row in tsv {
+            match Class::from_row(row, start_time_grid) {
+                Ok(class) => classes.push(class),
+                Err(err) => {
+                    println!(
+                        "Warning! Skipping line {} of {}: {err:#}",
+                        row.line_number(),
+                        tsv.path()
+                    );
+                    num_skipped += 1;
+                }
+            }
+        }
+        if num_skipped > 0 {
+            println!("Skipped {num_skipped} bad classes");
+        }
+
+        Ok(classes)
+    }
+
+    // The days the class could run on, starting with the one from the timetable
+    pub fn candidate_days(&self) -> impl Iterator<Item = Day> + '_ {
+        std::iter::once(self.day).chain(self.alternative_days.iter().copied())
+    }
+
+    // Sessions for the timetabled day use the class's name, and those for the
+    // alternative days have the day added so they can be told apart
+    pub fn name_on_day(&self, day: Day) -> String {
+        if day == self.day {
+            self.name.clone()
+        } else {
+            format!("{}@{}", self.name, day)
+        }
     }
 }
@@ -1,24 +1,99 @@
 use std::fmt::{self};
-use std::{fs, path::Path};
+use std::{collections::BTreeSet, fs, path::PathBuf};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use enum_map::EnumMap;
-use serde::de::Error as _;
+use itertools::Itertools;
+use serde::de::{value::StrDeserializer, Error as _, IntoDeserializer, Unexpected};
 use serde::Deserialize;
-use strum::IntoStaticStr;
+use strum::{EnumIter, IntoEnumIterator, IntoStaticStr};
 
-pub type CostValue = u64;
+use crate::session::{Session, SessionId};
+use crate::utils::{matches_glob_list, TimeOfDay};
 
-#[derive(Debug, Deserialize, Default)]
+pub type CostValue = u128;
+
+// How much cheaper a preferred session is than a possible one with --neutral-possible
+const NEUTRAL_PREFERRED_REWARD: CostValue = 1;
+
+#[derive(Debug, Default)]
 enum CostPossibility {
-    #[serde(alias = "inf", alias = "infinity")]
     #[default]
     Infinity,
-    #[serde(untagged)]
     Value(CostValue),
 }
 
-#[derive(Debug, enum_map::Enum, Deserialize, IntoStaticStr, Clone, Copy)]
+// A term of a cost expression, which is an integer with an optional k or M suffix
+fn parse_cost_term(term: &str) -> Option<CostValue> {
+    let (number, multiplier) = if let Some(number) = term.strip_suffix(['k', 'K']) {
+        (number, 1_000)
+    } else if let Some(number) = term.strip_suffix('M') {
+        (number, 1_000_000)
+    } else {
+        (term, 1)
+    };
+    number
+        .trim()
+        .replace('_', "")
+        .parse::<CostValue>()
+        .ok()?
+        .checked_mul(multiplier)
+}
+
+// Besides plain integers, costs can be `inf` or a product of terms like `5k` or `2 * 1M`
+impl<'de> Deserialize<'de> for CostPossibility {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(CostPossibilityVisitor)
+    }
+}
+
+struct CostPossibilityVisitor;
+
+impl serde::de::Visitor<'_> for CostPossibilityVisitor {
+    type Value = CostPossibility;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "a non-negative integer, \"inf\", or an expression like \"5k\" or \"2 * 1M\""
+        )
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(CostPossibility::Value(value.into()))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        CostValue::try_from(value)
+            .map(CostPossibility::Value)
+            .map_err(|_| E::invalid_value(Unexpected::Signed(value), &self))
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        let value_trimmed = value.trim();
+        if ["inf", "infinity"]
+            .iter()
+            .any(|name| value_trimmed.eq_ignore_ascii_case(name))
+        {
+            return Ok(CostPossibility::Infinity);
+        }
+
+        value_trimmed
+            .split('*')
+            .try_fold(1 as CostValue, |product, term| {
+                product.checked_mul(parse_cost_term(term.trim())?)
+            })
+            .map(CostPossibility::Value)
+            .ok_or_else(|| E::invalid_value(Unexpected::Str(value), &self))
+    }
+}
+
+#[derive(
+    Debug, enum_map::Enum, Deserialize, IntoStaticStr, EnumIter, Clone, Copy, PartialEq, Eq,
+)]
 #[serde(rename_all = "snake_case")]
 pub enum Constraint {
     AssignedPreferred,
@@ -32,24 +107,99 @@ pub enum Constraint {
     AboveMaxTut,
     AboveMaxLab,
     AboveMaxClass,
+    BelowMinHours,
+    AboveMaxHours,
     DirectOverlap,
     PaddedOverlap,
     SameDayOverlap,
     MismatchedInitialSolution,
+    CourseTeamPreferenceUnmet,
+    UnsocialHours,
+    TutorEnrolledInClass,
+    ClassPartiallyStaffed,
+    LabAssistWithoutLead,
+    PreferContiguousClassBlocks,
+    TooManyDistinctStartTimes,
+    DaySpanTooLong,
+    TooManyOnOneDay,
+    ContinuousTeachingWithoutBreak,
+    ContinuityBroken,
+    PoorExperienceMix,
+    NotReturningTutor,
+    MultipleDayVariants,
+    ClassLeadNotAssisting,
+    ReserveInstructorUsed,
+    CrossCampusTransition,
 }
 
 impl Constraint {
+    // The name used for the constraint in costs.toml
+    pub fn toml_name(self) -> String {
+        let variant_name: &str = self.into();
+        let mut toml_name = String::new();
+        for (idx, ch) in variant_name.chars().enumerate() {
+            if ch.is_ascii_uppercase() && idx > 0 {
+                toml_name.push('_');
+            }
+            toml_name.push(ch.to_ascii_lowercase());
+        }
+        toml_name
+    }
+
     fn default_value(self) -> Option<CostPossibility> {
         Some(match self {
             Self::AssignedPreferred => CostPossibility::Value(0),
             Self::AssignedImpossible => CostPossibility::Infinity,
             Self::MismatchedInitialSolution => CostPossibility::Value(0),
+            Self::BelowMinHours => CostPossibility::Value(0),
+            Self::AboveMaxHours => CostPossibility::Value(0),
+            Self::CourseTeamPreferenceUnmet => CostPossibility::Value(0),
+            Self::UnsocialHours => CostPossibility::Value(0),
+            Self::TutorEnrolledInClass => CostPossibility::Infinity,
+            Self::ClassPartiallyStaffed => CostPossibility::Value(0),
+            Self::LabAssistWithoutLead => CostPossibility::Value(0),
+            Self::PreferContiguousClassBlocks => CostPossibility::Value(0),
+            Self::TooManyDistinctStartTimes => CostPossibility::Value(0),
+            Self::DaySpanTooLong => CostPossibility::Value(0),
+            Self::TooManyOnOneDay => CostPossibility::Value(0),
+            Self::ContinuousTeachingWithoutBreak => CostPossibility::Value(0),
+            Self::ContinuityBroken => CostPossibility::Value(0),
+            Self::PoorExperienceMix => CostPossibility::Value(0),
+            Self::NotReturningTutor => CostPossibility::Value(0),
+            Self::MultipleDayVariants => CostPossibility::Infinity,
+            Self::ClassLeadNotAssisting => CostPossibility::Value(0),
+            Self::ReserveInstructorUsed => CostPossibility::Value(0),
+            Self::CrossCampusTransition => CostPossibility::Value(0),
             _ => return None,
         })
     }
 }
 
-type CostCountNum = u32;
+// A costs.toml listing every constraint, with the ones which have defaults commented
+// out and the rest (which must be set) given a placeholder of 0
+pub fn costs_toml_template() -> String {
+    let mut template = String::from(
+        "# Costs are non-negative integers or \"inf\", see costs.example.toml for what each\n\
+         # constraint means. Commented out constraints are set to their default.\n\n",
+    );
+
+    for constraint in Constraint::iter() {
+        let constraint_name = constraint.toml_name();
+        match constraint.default_value() {
+            Some(CostPossibility::Value(val)) => {
+                template.push_str(&format!("# {constraint_name} = {val}\n"))
+            }
+            Some(CostPossibility::Infinity) => {
+                template.push_str(&format!("# {constraint_name} = \"inf\"\n"))
+            }
+            None => template.push_str(&format!("{constraint_name} = 0 # required\n")),
+        }
+    }
+
+    template
+}
+
+pub type CostCountNum = u32;
 
 pub struct CostCount {
     counts: EnumMap<Constraint, CostCountNum>,
@@ -57,7 +207,7 @@ pub struct CostCount {
 
 impl CostCount {
     pub fn add_cost(&mut self, category: Constraint, count: impl Into<CostCountNum>) {
-        self.counts[category] += count.into();
+        self.counts[category] = self.counts[category].saturating_add(count.into());
     }
 
     pub fn add_cost_1(&mut self, category: Constraint) {
@@ -65,19 +215,26 @@ impl CostCount {
     }
 
     pub fn total_cost(&self, config: &CostConfig) -> Option<CostValue> {
+        let Some(tiers) = &config.lexicographic_tiers else {
+            return self
+                .counts
+                .iter()
+                .map(|(constraint, &count)| config.cost_of(constraint, count))
+                .sum::<Option<CostValue>>();
+        };
+
+        let mut tier_costs = [0 as CostValue; MAX_LEXICOGRAPHIC_TIERS];
+        for (constraint, &count) in &self.counts {
+            let tier_cost = &mut tier_costs[tiers.tier_of[constraint]];
+            *tier_cost = tier_cost.saturating_add(config.cost_of(constraint, count)?);
+        }
+        Some(tiers.combine(&tier_costs))
+    }
+
+    pub fn counts(&self) -> impl Iterator<Item = (Constraint, CostCountNum)> + '_ {
         self.counts
             .iter()
-            .map(|(constraint, &count)| match config.map[constraint] {
-                CostPossibility::Value(val) => (count as CostValue).checked_mul(val),
-                CostPossibility::Infinity => {
-                    if count > 0 {
-                        None
-                    } else {
-                        Some(0)
-                    }
-                }
-            })
-            .sum::<Option<CostValue>>()
+            .map(|(constraint, &count)| (constraint, count))
     }
 
     pub fn new() -> Self {
@@ -97,17 +254,282 @@ impl fmt::Display for CostCount {
     }
 }
 
+const MAX_LEXICOGRAPHIC_TIERS: usize = 4;
+
+// Solutions are compared by the cost of the first tier, then the second tier and so
+// on. The tier costs are packed into one CostValue (the first tier in the highest
+// bits) so the solver doesn't need to know about them, which means each tier's cost
+// is capped at what fits in its share of the bits (32 bits with 4 tiers).
+#[derive(Debug)]
+pub struct LexicographicTiers {
+    tier_of: EnumMap<Constraint, usize>,
+    num_tiers: usize,
+}
+
+impl LexicographicTiers {
+    // Constraints which aren't in any of the tiers are put in the last one
+    fn from_tiers(tiers: &[Vec<Constraint>]) -> Result<Self, String> {
+        if tiers.is_empty() || tiers.len() > MAX_LEXICOGRAPHIC_TIERS {
+            return Err(format!(
+                "lexicographic_tiers should have between 1 and {MAX_LEXICOGRAPHIC_TIERS} tiers"
+            ));
+        }
+
+        let mut tier_of: EnumMap<Constraint, Option<usize>> = EnumMap::default();
+        for (tier, constraints) in tiers.iter().enumerate() {
+            for &constraint in constraints {
+                if tier_of[constraint].is_some() {
+                    return Err(format!(
+                        "{} is in more than one of the lexicographic_tiers",
+                        constraint.toml_name()
+                    ));
+                }
+                tier_of[constraint] = Some(tier);
+            }
+        }
+
+        Ok(LexicographicTiers {
+            tier_of: tier_of.map(|_, tier| tier.unwrap_or(tiers.len() - 1)),
+            num_tiers: tiers.len(),
+        })
+    }
+
+    fn bits_per_tier(&self) -> u32 {
+        CostValue::BITS / self.num_tiers as u32
+    }
+
+    fn max_tier_cost(&self) -> CostValue {
+        CostValue::MAX >> (CostValue::BITS - self.bits_per_tier())
+    }
+
+    fn combine(&self, tier_costs: &[CostValue]) -> CostValue {
+        let bits = self.bits_per_tier();
+        let max_tier_cost = self.max_tier_cost();
+        tier_costs[..self.num_tiers]
+            .iter()
+            .fold(0, |combined: CostValue, &tier_cost| {
+                combined.checked_shl(bits).unwrap_or(0) | tier_cost.min(max_tier_cost)
+            })
+    }
+}
+
 #[derive(Debug)]
 pub struct CostConfig {
     map: EnumMap<Constraint, CostPossibility>,
+
+    // sessions starting before/after these times count as unsocial hours
+    pub earliest_comfortable: Option<TimeOfDay>,
+    pub latest_comfortable: Option<TimeOfDay>,
+
+    // each entry is a comma separated list of class name globs, and the assigned
+    // sessions matching an entry should be clustered together on each day
+    pub contiguous_class_groups: Vec<String>,
+
+    // the longest time (in hours) from an instructor's first start to last end on a day
+    pub max_day_span: Option<u8>,
+
+    // the most hours an instructor can teach back to back before needing a break
+    pub max_continuous_hours: Option<u8>,
+
+    // multiplies senior tutors' assigned_possible/assigned_dislike costs so their
+    // preferences win ties
+    pub senior_preference_weight: CostCountNum,
+
+    // the largest fraction of concurrent sessions which should have new tutors
+    pub max_new_tutor_fraction: f64,
+
+    // if given, solutions are ranked by these tiers of constraints in order rather
+    // than the plain sum of all costs
+    pub lexicographic_tiers: Option<LexicographicTiers>,
 }
 
 impl CostConfig {
-    pub fn read_from_toml(path: &Path) -> Result<Self> {
-        let toml_string = fs::read_to_string(path)
-            .with_context(|| anyhow!("failed to read costs toml at {}", path.display()))?;
-        toml::from_str(&toml_string)
-            .with_context(|| anyhow!("failed to parse cost config at {}", path.display()))
+    // Each toml is either a flat map of constraints to costs, or has named
+    // `[profiles.NAME]` tables which override any top level values. The files are
+    // layered in order, so later files override individual values from earlier ones
+    // (including within each profile), and anything not in any file gets its default.
+    pub fn read_from_tomls(paths: &[PathBuf], profile: Option<&str>) -> Result<Self> {
+        let mut table = toml::Table::new();
+        // every profile name in any of the files, and whether the requested one was found
+        let mut profile_names: Option<BTreeSet<String>> = None;
+        let mut found_profile = false;
+
+        // each file's profile is applied over that file, before the next file is
+        // layered on top, so a later file's values win over an earlier file's profile
+        for path in paths {
+            let toml_string = fs::read_to_string(path)
+                .with_context(|| anyhow!("failed to read costs toml at {}", path.display()))?;
+            let mut layer: toml::Table = toml::from_str(&toml_string)
+                .with_context(|| anyhow!("failed to parse cost config at {}", path.display()))?;
+
+            if let Some(layer_profiles) = layer.remove("profiles") {
+                let toml::Value::Table(mut layer_profiles) = layer_profiles else {
+                    bail!("`profiles` in {} should be a table", path.display())
+                };
+                profile_names
+                    .get_or_insert_with(BTreeSet::new)
+                    .extend(layer_profiles.keys().cloned());
+
+                if let Some(profile) = profile {
+                    match layer_profiles.remove(profile) {
+                        Some(toml::Value::Table(layer_profile)) => {
+                            layer.extend(layer_profile);
+                            found_profile = true;
+                        }
+                        Some(_) => bail!(
+                            "cost profile {profile:?} in {} should be a table",
+                            path.display()
+                        ),
+                        None => {}
+                    }
+                }
+            }
+
+            table.extend(layer);
+        }
+
+        let paths_description = paths.iter().map(|path| path.display()).join(", ");
+        match (profile_names, profile) {
+            (None, None) => {}
+            (None, Some(profile)) => {
+                bail!(
+                    "cost profile {profile:?} was requested but {paths_description} doesn't have any profiles"
+                )
+            }
+            (Some(profile_names), None) => {
+                bail!(
+                    "{paths_description} has cost profiles ({}), choose one with --cost-profile",
+                    profile_names.iter().join(", ")
+                )
+            }
+            (Some(profile_names), Some(profile)) => {
+                if !found_profile {
+                    bail!(
+                        "cost profile {profile:?} isn't in {paths_description} (available profiles: {})",
+                        profile_names.iter().join(", ")
+                    )
+                }
+            }
+        }
+
+        toml::Value::Table(table)
+            .try_into()
+            .with_context(|| anyhow!("failed to parse cost config from {paths_description}"))
+    }
+
+    // Treat `possible` as the baseline with a small reward for `preferred`. Costs
+    // can't be negative, so rather than giving preferred a negative cost, the rest
+    // of the scale is shifted up by the reward instead. Every session is either
+    // assigned or unassigned, so this only changes the total by a constant.
+    pub fn make_possible_neutral(&mut self) {
+        self.map[Constraint::AssignedPreferred] = CostPossibility::Value(0);
+        self.map[Constraint::AssignedPossible] = CostPossibility::Value(NEUTRAL_PREFERRED_REWARD);
+        for constraint in [
+            Constraint::AssignedDislike,
+            Constraint::AssignedImpossible,
+            Constraint::UnassignedSession,
+        ] {
+            if let CostPossibility::Value(val) = &mut self.map[constraint] {
+                *val = val.saturating_add(NEUTRAL_PREFERRED_REWARD);
+            }
+        }
+    }
+
+    // Warn about cost settings which make the solver's job meaningless
+    pub fn warn_about_flat_assignment_costs(&self) {
+        let value = |constraint| match self.map[constraint] {
+            CostPossibility::Value(val) => Some(val),
+            CostPossibility::Infinity => None,
+        };
+
+        let preferred = value(Constraint::AssignedPreferred);
+        let possible = value(Constraint::AssignedPossible);
+        let dislike = value(Constraint::AssignedDislike);
+
+        if preferred == Some(0) && possible == Some(0) && dislike == Some(0) {
+            println!(
+                "Warning: assigned_preferred, assigned_possible and assigned_dislike are all 0, so availability preferences will be ignored and only the other costs will guide the solver"
+            );
+        } else if preferred == possible {
+            println!(
+                "Warning: assigned_preferred and assigned_possible have the same cost, so preferred sessions won't be favoured (costs can't be negative, so raise the other assignment costs instead)"
+            );
+        }
+    }
+
+    // The cost of violating the constraint `count` times, or None if it's infinite
+    pub fn cost_of(&self, constraint: Constraint, count: CostCountNum) -> Option<CostValue> {
+        match self.map[constraint] {
+            CostPossibility::Value(val) => (count as CostValue).checked_mul(val),
+            CostPossibility::Infinity => {
+                if count > 0 {
+                    None
+                } else {
+                    Some(0)
+                }
+            }
+        }
+    }
+
+    // Warn about soft constraints with costs so large that they act like `inf`: either
+    // a plausible number of violations would overflow (making the total cost None
+    // anyway), or a single violation outweighs every other soft cost put together.
+    // `max_count` is a generous guess at the most times any constraint could be counted.
+    pub fn warn_about_effectively_hard_costs(&self, max_count: CostCountNum) {
+        // outweighing the lower tiers is the point of lexicographic tiers, but each
+        // tier's cost is capped so a tier that could go over its cap would tie
+        if let Some(tiers) = &self.lexicographic_tiers {
+            let mut tier_max_costs = [Some(0 as CostValue); MAX_LEXICOGRAPHIC_TIERS];
+            for (constraint, possibility) in &self.map {
+                if let CostPossibility::Value(val) = *possibility {
+                    let tier_max_cost = &mut tier_max_costs[tiers.tier_of[constraint]];
+                    *tier_max_cost = tier_max_cost
+                        .and_then(|total| total.checked_add(val.checked_mul(max_count.into())?));
+                }
+            }
+            for (tier, tier_max_cost) in tier_max_costs[..tiers.num_tiers].iter().enumerate() {
+                if tier_max_cost.is_none_or(|cost| cost > tiers.max_tier_cost()) {
+                    println!(
+                        "Warning: the costs in lexicographic tier {} could add up to more than {} when counted {max_count} times, beyond which solutions with different costs in that tier compare as equal",
+                        tier + 1,
+                        tiers.max_tier_cost()
+                    );
+                }
+            }
+            return;
+        }
+
+        let soft_costs = self
+            .map
+            .iter()
+            .filter(|&(constraint, _)| self.should_count(constraint))
+            .filter_map(|(constraint, possibility)| match *possibility {
+                CostPossibility::Value(val) => Some((constraint, val)),
+                CostPossibility::Infinity => None,
+            })
+            .collect::<Vec<_>>();
+
+        for &(constraint, val) in &soft_costs {
+            let constraint_name: &str = constraint.into();
+            if val.checked_mul(max_count as CostValue).is_none() {
+                println!(
+                    "Warning: {constraint_name} = {val} could overflow the total cost when counted {max_count} times, use \"inf\" if it should never happen"
+                );
+                continue;
+            }
+
+            let others_total = soft_costs
+                .iter()
+                .filter(|&&(other, _)| other != constraint)
+                .try_fold(0 as CostValue, |total, &(_, other_val)| {
+                    total.checked_add(other_val.checked_mul(max_count as CostValue)?)
+                });
+            if others_total.is_some_and(|others_total| others_total > 0 && val > others_total) {
+                println!(
+                    "Warning: {constraint_name} = {val} outweighs every other cost combined so it's effectively a hard constraint, consider \"inf\" instead"
+                );
+            }
+        }
     }
 
     pub fn should_count(&self, constraint: Constraint) -> bool {
@@ -116,6 +538,28 @@ impl CostConfig {
             CostPossibility::Value(val) => val != 0,
         }
     }
+
+    pub fn is_unsocial_start(&self, start_time: TimeOfDay) -> bool {
+        self.earliest_comfortable
+            .is_some_and(|earliest| start_time < earliest)
+            || self
+                .latest_comfortable
+                .is_some_and(|latest| start_time > latest)
+    }
+
+    // The sessions in each of the `contiguous_class_groups`, in the same order
+    pub fn contiguous_group_sessions(&self, sessions: &[Session]) -> Vec<Vec<SessionId>> {
+        self.contiguous_class_groups
+            .iter()
+            .map(|group| {
+                sessions
+                    .iter()
+                    .filter(|session| matches_glob_list(group, &session.class_name))
+                    .map(|session| session.session_id)
+                    .collect()
+            })
+            .collect()
+    }
 }
 
 // Although EnumMap implements Deserialize it doesn't quite suit what we need
@@ -142,13 +586,96 @@ impl<'de> serde::de::Visitor<'de> for CostConfigVisitor {
         self,
         mut access: M,
     ) -> Result<Self::Value, M::Error> {
-        let mut entries: EnumMap<Constraint, Option<_>> = EnumMap::default();
+        let mut entries: EnumMap<Constraint, Option<CostPossibility>> = EnumMap::default();
+        let mut earliest_comfortable = None;
+        let mut latest_comfortable = None;
+        let mut contiguous_class_groups = None;
+        let mut max_day_span = None;
+        let mut max_continuous_hours = None;
+        let mut senior_preference_weight = None;
+        let mut max_new_tutor_fraction = None;
+        let mut lexicographic_tiers = None;
+
+        fn read_time<'de, M: serde::de::MapAccess<'de>>(
+            access: &mut M,
+            setting: &mut Option<TimeOfDay>,
+            name: &'static str,
+        ) -> Result<(), M::Error> {
+            if setting.is_some() {
+                return Err(M::Error::duplicate_field(name));
+            }
+            let hour: u8 = access.next_value()?;
+            *setting = Some(TimeOfDay::from_hour(hour).ok_or_else(|| {
+                M::Error::custom(format!("{name} should be an hour between 0 and 23"))
+            })?);
+            Ok(())
+        }
 
-        while let Some((constraint, value)) = access.next_entry()? {
-            if entries[constraint].is_some() {
-                return Err(M::Error::duplicate_field(constraint.into()));
+        // besides the constraint costs there are a few other settings in the same map
+        while let Some(key) = access.next_key::<String>()? {
+            match key.as_str() {
+                "earliest_comfortable" => read_time(
+                    &mut access,
+                    &mut earliest_comfortable,
+                    "earliest_comfortable",
+                )?,
+                "latest_comfortable" => {
+                    read_time(&mut access, &mut latest_comfortable, "latest_comfortable")?
+                }
+                "contiguous_class_groups" => {
+                    if contiguous_class_groups.is_some() {
+                        return Err(M::Error::duplicate_field("contiguous_class_groups"));
+                    }
+                    contiguous_class_groups = Some(access.next_value()?);
+                }
+                "max_day_span" => {
+                    if max_day_span.is_some() {
+                        return Err(M::Error::duplicate_field("max_day_span"));
+                    }
+                    max_day_span = Some(access.next_value()?);
+                }
+                "max_continuous_hours" => {
+                    if max_continuous_hours.is_some() {
+                        return Err(M::Error::duplicate_field("max_continuous_hours"));
+                    }
+                    max_continuous_hours = Some(access.next_value()?);
+                }
+                "senior_preference_weight" => {
+                    if senior_preference_weight.is_some() {
+                        return Err(M::Error::duplicate_field("senior_preference_weight"));
+                    }
+                    senior_preference_weight = Some(access.next_value()?);
+                }
+                "max_new_tutor_fraction" => {
+                    if max_new_tutor_fraction.is_some() {
+                        return Err(M::Error::duplicate_field("max_new_tutor_fraction"));
+                    }
+                    let fraction: f64 = access.next_value()?;
+                    if !(0.0..=1.0).contains(&fraction) {
+                        return Err(M::Error::custom(
+                            "max_new_tutor_fraction should be between 0 and 1",
+                        ));
+                    }
+                    max_new_tutor_fraction = Some(fraction);
+                }
+                "lexicographic_tiers" => {
+                    if lexicographic_tiers.is_some() {
+                        return Err(M::Error::duplicate_field("lexicographic_tiers"));
+                    }
+                    let tiers: Vec<Vec<Constraint>> = access.next_value()?;
+                    lexicographic_tiers =
+                        Some(LexicographicTiers::from_tiers(&tiers).map_err(M::Error::custom)?);
+                }
+                _ => {
+                    let key_deserializer: StrDeserializer<M::Error> =
+                        key.as_str().into_deserializer();
+                    let constraint = Constraint::deserialize(key_deserializer)?;
+                    if entries[constraint].is_some() {
+                        return Err(M::Error::duplicate_field(constraint.into()));
+                    }
+                    entries[constraint] = Some(access.next_value()?);
+                }
             }
-            entries[constraint] = Some(value);
         }
 
         Ok(CostConfig {
@@ -161,6 +688,128 @@ impl<'de> serde::de::Visitor<'de> for CostConfigVisitor {
                     },
                 )
                 .collect::<Result<_, _>>()?,
+            earliest_comfortable,
+            latest_comfortable,
+            contiguous_class_groups: contiguous_class_groups.unwrap_or_default(),
+            max_day_span,
+            max_continuous_hours,
+            senior_preference_weight: senior_preference_weight.unwrap_or(1),
+            max_new_tutor_fraction: max_new_tutor_fraction.unwrap_or(0.5),
+            lexicographic_tiers,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_costs_tomls(test_name: &str, tomls: &[String]) -> Vec<PathBuf> {
+        let dir = std::env::temp_dir().join(format!(
+            "tlb_auto_scheduler_{test_name}_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        tomls
+            .iter()
+            .enumerate()
+            .map(|(idx, contents)| {
+                let path = dir.join(format!("costs{idx}.toml"));
+                fs::write(&path, contents).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    #[test]
+    fn later_files_override_an_earlier_files_profile() -> Result<()> {
+        let base = format!(
+            "{}\n[profiles.strict]\nunassigned_session = 1\nassigned_dislike = 1\n",
+            include_str!("../costs.example.toml")
+        );
+        let paths = write_costs_tomls("profiles", &[base, "unassigned_session = 2\n".into()]);
+
+        let config = CostConfig::read_from_tomls(&paths, Some("strict"))?;
+        assert_eq!(config.cost_of(Constraint::UnassignedSession, 1), Some(2));
+        assert_eq!(config.cost_of(Constraint::AssignedDislike, 1), Some(1));
+
+        assert!(CostConfig::read_from_tomls(&paths, None).is_err());
+        assert!(CostConfig::read_from_tomls(&paths, Some("missing")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn from_tiers_puts_unlisted_constraints_in_the_last_tier() {
+        let tiers = LexicographicTiers::from_tiers(&[
+            vec![Constraint::AssignedImpossible],
+            vec![Constraint::AssignedDislike],
+            vec![],
+        ])
+        .unwrap();
+        assert_eq!(tiers.num_tiers, 3);
+        assert_eq!(tiers.tier_of[Constraint::AssignedImpossible], 0);
+        assert_eq!(tiers.tier_of[Constraint::AssignedDislike], 1);
+        assert_eq!(tiers.tier_of[Constraint::UnassignedSession], 2);
+
+        assert!(LexicographicTiers::from_tiers(&[]).is_err());
+        assert!(
+            LexicographicTiers::from_tiers(&vec![vec![]; MAX_LEXICOGRAPHIC_TIERS + 1]).is_err()
+        );
+        assert!(LexicographicTiers::from_tiers(&[
+            vec![Constraint::AssignedDislike],
+            vec![Constraint::AssignedDislike],
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn combine_ranks_earlier_tiers_first() {
+        let tiers = LexicographicTiers::from_tiers(&vec![vec![]; MAX_LEXICOGRAPHIC_TIERS]).unwrap();
+        let big = 100_000 * 1000;
+        assert!(tiers.combine(&[1, 0, 0, 0]) > tiers.combine(&[0, big, big, big]));
+        assert!(tiers.combine(&[0, 1, 0, 0]) > tiers.combine(&[0, 0, big, big]));
+        assert!(tiers.combine(&[2 * big, 0, 0, 0]) > tiers.combine(&[big, 0, 0, 0]));
+        assert!(tiers.combine(&[0, 0, 0, 2]) > tiers.combine(&[0, 0, 0, 1]));
+
+        // only costs beyond each tier's cap tie
+        let max = tiers.max_tier_cost();
+        assert!(max >= CostValue::from(u32::MAX));
+        assert!(tiers.combine(&[max - 1, 0, 0, 0]) < tiers.combine(&[max, 0, 0, 0]));
+        assert_eq!(
+            tiers.combine(&[max, 0, 0, 0]),
+            tiers.combine(&[max + 1, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn example_tiers_prefer_assigning_preferred_sessions() {
+        let config: CostConfig = toml::from_str(&include_str!("../costs.example.toml").replacen(
+            "# lexicographic_tiers = [\n#     [\"assigned_impossible\", \"direct_overlap\"],\n#     [\"assigned_possible\", \"assigned_dislike\", \"unassigned_session\"],\n#     [],\n# ]",
+            "lexicographic_tiers = [\n    [\"assigned_impossible\", \"direct_overlap\"],\n    [\"assigned_possible\", \"assigned_dislike\", \"unassigned_session\"],\n    [],\n]",
+            1,
+        ))
+        .unwrap();
+        assert!(config.lexicographic_tiers.is_some());
+
+        let costs = |counts: &[(Constraint, CostCountNum)]| {
+            let mut cost_count = CostCount::new();
+            for &(constraint, count) in counts {
+                cost_count.add_cost(constraint, count);
+            }
+            cost_count.total_cost(&config).unwrap()
+        };
+        let all_preferred = costs(&[(Constraint::AssignedPreferred, 10)]);
+        let one_possible = costs(&[
+            (Constraint::AssignedPreferred, 9),
+            (Constraint::AssignedPossible, 1),
+        ]);
+        let one_unassigned = costs(&[
+            (Constraint::AssignedPreferred, 9),
+            (Constraint::UnassignedSession, 1),
+        ]);
+        let one_impossible = costs(&[(Constraint::AssignedImpossible, 1)]);
+        assert!(all_preferred < one_possible);
+        assert!(one_possible < one_unassigned);
+        assert!(one_unassigned < one_impossible);
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::{anyhow, Context, Result};
+use bit_set::BitSet;
+
+use crate::{
+    instructor::{Instructor, InstructorId},
+    session::{Session, SessionId},
+    tsv::Tsv,
+    utils::matches_glob_list,
+};
+
+// Which sessions each instructor is a student in, and so can't tutor
+#[derive(Default)]
+pub struct Enrolments {
+    num_instructors: usize,
+    enrolled: BitSet,
+}
+
+impl Enrolments {
+    pub fn from_tsv(tsv: &Tsv, instructors: &[Instructor], sessions: &[Session]) -> Result<Self> {
+        let num_instructors = instructors.len();
+        let mut enrolled = BitSet::with_capacity(num_instructors * sessions.len());
+
+        for row in tsv {
+            let zid = row.get("zid")?.trim();
+            let class_pattern = row.get("class")?;
+
+            let instructor = instructors
+                .iter()
+                .find(|instructor| instructor.zid == zid)
+                .with_context(|| anyhow!("cannot find enrolled instructor {zid}"))?;
+
+            for session in sessions {
+                if matches_glob_list(class_pattern, &session.class_name) {
+                    enrolled.insert(
+                        session.session_id.raw_index() * num_instructors
+                            + instructor.instructor_id.raw_index(),
+                    );
+                }
+            }
+        }
+
+        Ok(Enrolments {
+            num_instructors,
+            enrolled,
+        })
+    }
+
+    pub fn num_enrolments(&self) -> usize {
+        self.enrolled.len()
+    }
+
+    pub fn is_enrolled(&self, session: SessionId, instructor: InstructorId) -> bool {
+        self.enrolled
+            .contains(session.raw_index() * self.num_instructors + instructor.raw_index())
+    }
+}
@@ -1,8 +1,14 @@
 use crate::{
     availabilities::AvailabilityMatrix,
-    costs::{Constraint, CostConfig, CostCount},
+    class_prefs::ClassPreferences,
+    costs::{Constraint, CostConfig, CostCount, CostCountNum, CostValue},
+    enrolments::Enrolments,
+    history::TeachingHistory,
     instructor::{Instructor, InstructorId},
-    session::{OverlapMatrix, Session, SessionId, SessionType},
+    session::{
+        ConcurrentGroups, ContinuityGroups, DayVariants, LabLeads, OverlapLevel, OverlapLevels,
+        OverlapMatrix, Session, SessionId, SessionType,
+    },
     talloc::Availability,
     utils::TwoCombIter,
 };
@@ -14,12 +20,178 @@ pub struct Problem<'a> {
     pub availabilities: &'a AvailabilityMatrix,
 
     pub overlap_sharp: &'a OverlapMatrix,
-    pub overlap_padded: &'a OverlapMatrix,
-    pub overlap_same_day: &'a OverlapMatrix,
+    pub overlap_levels: &'a OverlapLevels,
+    pub lab_leads: &'a LabLeads,
+    // the sessions in each of the cost config's `contiguous_class_groups`
+    pub contiguous_groups: &'a [Vec<SessionId>],
+    pub continuity_groups: &'a ContinuityGroups,
+    pub concurrent_groups: &'a ConcurrentGroups,
+    pub day_variants: &'a DayVariants,
 
     pub cost_config: &'a CostConfig,
 
     pub initial_solution: &'a Solution,
+    pub mismatch_weights: &'a [u32],
+    // the sessions which the solver is allowed to change, hardest to staff first
+    pub unlocked_sessions: &'a [SessionId],
+    // when false the solver never leaves a session unassigned, so the initial
+    // solution must be complete
+    pub allow_unassigned: bool,
+    // if set, only this instructor can be given new sessions (see --assign-only)
+    pub focus_instructor: Option<InstructorId>,
+
+    pub class_preferences: &'a ClassPreferences,
+    pub enrolments: &'a Enrolments,
+    pub history: &'a TeachingHistory,
+}
+
+impl Problem<'_> {
+    pub fn can_be_given_sessions(&self, instructor_id: InstructorId) -> bool {
+        self.focus_instructor
+            .is_none_or(|focus_instructor| focus_instructor == instructor_id)
+    }
+
+    // A cheap lower bound on the best achievable cost, from giving each session its
+    // cheapest possible assignment independently and adding the minimums which
+    // instructors can't meet without being given impossible sessions. Everything else
+    // is assumed to cost nothing. None means every solution has infinite cost.
+    pub fn cost_lower_bound(&self) -> Option<CostValue> {
+        let config = self.cost_config;
+        let assignment_cost = |session_id: SessionId, instructor: &Instructor| {
+            let is_senior = instructor
+                .seniority
+                .as_ref()
+                .is_some_and(|seniority| seniority.is_senior_tutor);
+            let weight = if is_senior {
+                config.senior_preference_weight
+            } else {
+                1
+            };
+            match self
+                .availabilities
+                .get_availability(session_id, instructor.instructor_id)
+            {
+                Availability::Impossible => config.cost_of(Constraint::AssignedImpossible, 1),
+                Availability::Dislike => config.cost_of(Constraint::AssignedDislike, weight),
+                Availability::Possible => config.cost_of(Constraint::AssignedPossible, weight),
+                Availability::Preferred => config.cost_of(Constraint::AssignedPreferred, 1),
+            }
+        };
+
+        let mut is_unlocked = vec![false; self.sessions.len()];
+        for session_id in self.unlocked_sessions {
+            is_unlocked[session_id.raw_index()] = true;
+        }
+
+        // any session of a class with alternative days might end up on an unused day
+        let mut may_be_inactive = vec![false; self.sessions.len()];
+        for session_id in self.day_variants.groups.iter().flatten().flatten() {
+            may_be_inactive[session_id.raw_index()] = true;
+        }
+
+        let mut bound: CostValue = 0;
+        // the most which any session that can be reassigned adds to the bound
+        let mut max_unlocked_cheapest = None;
+        for session in self.sessions {
+            let session_id = session.session_id;
+            if may_be_inactive[session_id.raw_index()] {
+                if is_unlocked[session_id.raw_index()] {
+                    max_unlocked_cheapest = max_unlocked_cheapest.max(Some(0));
+                }
+                continue;
+            }
+            let cheapest = if is_unlocked[session_id.raw_index()] {
+                self.instructors
+                    .iter()
+                    .filter_map(|instructor| assignment_cost(session_id, instructor))
+                    .chain(
+                        self.allow_unassigned
+                            .then(|| config.cost_of(Constraint::UnassignedSession, 1))
+                            .flatten(),
+                    )
+                    .min()
+            } else {
+                match self.initial_solution.assignment[session_id.raw_index()] {
+                    Some(instructor_id) => {
+                        assignment_cost(session_id, &self.instructors[instructor_id.raw_index()])
+                    }
+                    None => config.cost_of(Constraint::UnassignedSession, 1),
+                }
+            };
+            let cheapest = cheapest?;
+            if is_unlocked[session_id.raw_index()] {
+                max_unlocked_cheapest = max_unlocked_cheapest.max(Some(cheapest));
+            }
+            bound = bound.checked_add(cheapest)?;
+        }
+
+        // giving an instructor an impossible session costs at least this much more than
+        // that session's cheapest assignment, which was already counted
+        let impossible_session_cost = config
+            .cost_of(Constraint::AssignedImpossible, 1)
+            .zip(max_unlocked_cheapest)
+            .map(|(impossible, max_cheapest)| impossible.saturating_sub(max_cheapest));
+
+        for instructor in self.instructors {
+            let feasible = self
+                .sessions
+                .iter()
+                .filter(|session| {
+                    self.availabilities
+                        .get_availability(session.session_id, instructor.instructor_id)
+                        != Availability::Impossible
+                })
+                .collect::<Vec<_>>();
+            let feasible_tuts = feasible
+                .iter()
+                .filter(|session| matches!(session.typ, SessionType::TutLab))
+                .count();
+
+            let requirement = &instructor.class_type_requirement;
+            // None if staying below the minimums costs infinity
+            let mut below_min_cost: Option<CostValue> = Some(0);
+            for (num_feasible, min, constraint) in [
+                (
+                    feasible_tuts,
+                    requirement.min_tutes,
+                    Constraint::BelowMinTut,
+                ),
+                (
+                    feasible.len() - feasible_tuts,
+                    requirement.min_lab_assists,
+                    Constraint::BelowMinLab,
+                ),
+                (
+                    feasible.len(),
+                    requirement.min_total_classes,
+                    Constraint::BelowMinClass,
+                ),
+            ] {
+                let shortfall = (min as usize).saturating_sub(num_feasible) as CostCountNum;
+                below_min_cost = below_min_cost
+                    .zip(config.cost_of(constraint, shortfall))
+                    .and_then(|(total, cost)| total.checked_add(cost));
+            }
+
+            // the instructor either stays below their minimums, or is given at least one
+            // impossible session
+            let shortfall_cost = match self
+                .can_be_given_sessions(instructor.instructor_id)
+                .then_some(impossible_session_cost)
+                .flatten()
+            {
+                Some(impossible_session_cost) => Some(
+                    below_min_cost.map_or(impossible_session_cost, |below_min_cost| {
+                        below_min_cost.min(impossible_session_cost)
+                    }),
+                ),
+                None => below_min_cost,
+            };
+            bound = bound.checked_add(shortfall_cost?)?;
+        }
+
+        Some(bound)
+    }
 }
 
 #[derive(Default, Clone, PartialEq, Eq, Debug)]
@@ -30,6 +202,7 @@ pub struct Solution {
 
 pub struct EvalBuffer {
     instructor_allocations: Vec<Vec<SessionId>>,
+    inactive_sessions: Vec<bool>,
 }
 
 impl Solution {
@@ -56,28 +229,87 @@ impl Solution {
 
         let mut buffer = buffer.unwrap_or_else(|| EvalBuffer {
             instructor_allocations: vec![vec![]; problem.instructors.len()],
+            inactive_sessions: vec![false; problem.sessions.len()],
         });
         let instructor_allocations = &mut buffer.instructor_allocations;
         for alloc in instructor_allocations.iter_mut() {
             alloc.clear();
         }
 
+        // sessions on the unused days of classes with alternative days
+        let inactive_sessions = &mut buffer.inactive_sessions;
+        if !problem.day_variants.is_empty() {
+            let num_extra_days = problem
+                .day_variants
+                .mark_inactive(&self.assignment, inactive_sessions);
+            costs.add_cost(
+                Constraint::MultipleDayVariants,
+                num_extra_days as CostCountNum,
+            );
+        }
+
         for (assignment, session) in self.assignment.iter().copied().zip(problem.sessions) {
             match assignment {
                 Some(instructor_id) => {
                     let availability = problem
                         .availabilities
                         .get_availability(session.session_id, instructor_id);
-                    costs.add_cost_1(match availability {
-                        Availability::Impossible => Constraint::AssignedImpossible,
-                        Availability::Dislike => Constraint::AssignedDislike,
-                        Availability::Possible => Constraint::AssignedPossible,
-                        Availability::Preferred => Constraint::AssignedPreferred,
-                    });
+                    // costs can't be negative, so a senior tutor's preferences are favoured
+                    // by making their less preferred assignments more costly
+                    let is_senior = problem.instructors[instructor_id.raw_index()]
+                        .seniority
+                        .as_ref()
+                        .is_some_and(|seniority| seniority.is_senior_tutor);
+                    let weight = if is_senior {
+                        problem.cost_config.senior_preference_weight
+                    } else {
+                        1
+                    };
+                    match availability {
+                        Availability::Impossible => {
+                            costs.add_cost_1(Constraint::AssignedImpossible)
+                        }
+                        Availability::Dislike => {
+                            costs.add_cost(Constraint::AssignedDislike, weight)
+                        }
+                        Availability::Possible => {
+                            costs.add_cost(Constraint::AssignedPossible, weight)
+                        }
+                        Availability::Preferred => costs.add_cost_1(Constraint::AssignedPreferred),
+                    }
+
+                    if problem
+                        .enrolments
+                        .is_enrolled(session.session_id, instructor_id)
+                    {
+                        costs.add_cost_1(Constraint::TutorEnrolledInClass);
+                    }
+
+                    if problem.instructors[instructor_id.raw_index()].is_reserve {
+                        costs.add_cost_1(Constraint::ReserveInstructorUsed);
+                    }
+
+                    if problem.history.has_returning_tutor(session.session_id)
+                        && !problem
+                            .history
+                            .has_taught(session.session_id, instructor_id)
+                    {
+                        costs.add_cost_1(Constraint::NotReturningTutor);
+                    }
+
+                    if problem.cost_config.should_count(Constraint::UnsocialHours)
+                        && problem.cost_config.is_unsocial_start(session.start_time)
+                    {
+                        costs.add_cost_1(Constraint::UnsocialHours);
+                    }
 
                     instructor_allocations[instructor_id.raw_index()].push(session.session_id);
                 }
-                None => costs.add_cost_1(Constraint::UnassignedSession),
+                None => {
+                    if !inactive_sessions[session.session_id.raw_index()] {
+                        costs.add_cost_1(Constraint::UnassignedSession)
+                    }
+                }
             }
 
             if problem
@@ -88,12 +320,190 @@ impl Solution {
                     problem.initial_solution.assignment[session.session_id.raw_index()]
                 {
                     if Some(old_assignment) != assignment {
-                        costs.add_cost_1(Constraint::MismatchedInitialSolution);
+                        costs.add_cost(
+                            Constraint::MismatchedInitialSolution,
+                            problem.mismatch_weights[session.session_id.raw_index()],
+                        );
+                    }
+                }
+            }
+        }
+
+        if problem
+            .cost_config
+            .should_count(Constraint::LabAssistWithoutLead)
+        {
+            for (lab_session, lead_sessions) in &problem.lab_leads.leads {
+                if self.assignment[lab_session.raw_index()].is_some()
+                    && !lead_sessions
+                        .iter()
+                        .any(|lead_session| self.assignment[lead_session.raw_index()].is_some())
+                {
+                    costs.add_cost_1(Constraint::LabAssistWithoutLead);
+                }
+            }
+        }
+
+        if problem
+            .cost_config
+            .should_count(Constraint::ClassPartiallyStaffed)
+        {
+            // sessions from the same class are always next to each other
+            for class_sessions in problem
+                .sessions
+                .chunk_by(|a, b| a.class_name == b.class_name)
+            {
+                let num_assigned = class_sessions
+                    .iter()
+                    .filter(|session| self.assignment[session.session_id.raw_index()].is_some())
+                    .count();
+                if num_assigned > 0 && num_assigned < class_sessions.len() {
+                    costs.add_cost_1(Constraint::ClassPartiallyStaffed);
+                }
+            }
+        }
+
+        if problem
+            .cost_config
+            .should_count(Constraint::ClassLeadNotAssisting)
+        {
+            for class_sessions in problem
+                .sessions
+                .chunk_by(|a, b| a.class_name == b.class_name)
+            {
+                let assigned = class_sessions
+                    .iter()
+                    .filter_map(|session| {
+                        Some((session, self.assignment[session.session_id.raw_index()]?))
+                    })
+                    .collect::<Vec<_>>();
+                let leads = assigned
+                    .iter()
+                    .filter(|(session, _)| session.typ == SessionType::TutLab);
+                for &(lead_session, lead) in leads {
+                    let assists = assigned
+                        .iter()
+                        .filter(|(session, _)| session.typ == SessionType::LabAssist);
+                    for &(lab_session, assistant) in assists {
+                        // only charged when the lead could have taken the lab assist, so
+                        // not if it would clash with their tut+lab
+                        if lead != assistant
+                            && problem
+                                .availabilities
+                                .get_availability(lab_session.session_id, lead)
+                                != Availability::Impossible
+                            && problem
+                                .overlap_levels
+                                .get(lead_session.session_id, lab_session.session_id)
+                                != OverlapLevel::Sharp
+                        {
+                            costs.add_cost_1(Constraint::ClassLeadNotAssisting);
+                        }
                     }
                 }
             }
         }
 
+        if problem
+            .cost_config
+            .should_count(Constraint::PoorExperienceMix)
+        {
+            // each new tutor beyond the allowed fraction of a concurrent group is charged
+            for group in &problem.concurrent_groups.groups {
+                let mut num_assigned = 0;
+                let mut num_new = 0;
+                for session_id in group {
+                    if let Some(instructor_id) = self.assignment[session_id.raw_index()] {
+                        num_assigned += 1;
+                        if problem.instructors[instructor_id.raw_index()]
+                            .seniority
+                            .as_ref()
+                            .is_some_and(|seniority| seniority.is_new_tutor)
+                        {
+                            num_new += 1;
+                        }
+                    }
+                }
+
+                let max_new = (problem.cost_config.max_new_tutor_fraction * num_assigned as f64)
+                    .floor() as CostCountNum;
+                if num_new > max_new {
+                    costs.add_cost(Constraint::PoorExperienceMix, num_new - max_new);
+                }
+            }
+        }
+
+        if problem
+            .cost_config
+            .should_count(Constraint::ContinuityBroken)
+        {
+            // each extra instructor teaching part of a group is one break in continuity
+            for group in &problem.continuity_groups.groups {
+                let assigned = group
+                    .iter()
+                    .filter_map(|session_id| self.assignment[session_id.raw_index()])
+                    .collect::<Vec<_>>();
+                let num_instructors = assigned
+                    .iter()
+                    .enumerate()
+                    .filter(|&(idx, instructor_id)| !assigned[..idx].contains(instructor_id))
+                    .count();
+                if num_instructors > 1 {
+                    costs.add_cost(
+                        Constraint::ContinuityBroken,
+                        (num_instructors - 1) as CostCountNum,
+                    );
+                }
+            }
+        }
+
+        if problem
+            .cost_config
+            .should_count(Constraint::PreferContiguousClassBlocks)
+        {
+            // for each group and day, count the empty hours between the first and last
+            // assigned session, so the cost doesn't depend on the order of sessions
+            for group in problem.contiguous_groups {
+                let mut occupied_hours_by_day = [0u32; 5];
+                for &session_id in group {
+                    if self.assignment[session_id.raw_index()].is_none() {
+                        continue;
+                    }
+                    let session = &problem.sessions[session_id.raw_index()];
+                    let start = session.start_time.as_24_hours();
+                    for hour in start..start + session.duration.hours() {
+                        occupied_hours_by_day[session.day as usize] |= 1 << hour;
+                    }
+                }
+
+                for occupied_hours in occupied_hours_by_day {
+                    if occupied_hours == 0 {
+                        continue;
+                    }
+                    let span =
+                        32 - occupied_hours.leading_zeros() - occupied_hours.trailing_zeros();
+                    let num_gaps = span - occupied_hours.count_ones();
+                    costs.add_cost(Constraint::PreferContiguousClassBlocks, num_gaps);
+                }
+            }
+        }
+
+        if problem
+            .cost_config
+            .should_count(Constraint::CourseTeamPreferenceUnmet)
+        {
+            for preference in &problem.class_preferences.preferences {
+                let is_met = preference.sessions.iter().any(|session_id| {
+                    self.assignment[session_id.raw_index()].is_some_and(|instructor_id| {
+                        preference.instructors.contains(&instructor_id)
+                    })
+                });
+                if !is_met {
+                    costs.add_cost_1(Constraint::CourseTeamPreferenceUnmet);
+                }
+            }
+        }
+
         for (instructor, instructor_allocation) in
             problem.instructors.iter().zip(instructor_allocations)
         {
@@ -108,9 +518,16 @@ impl Solution {
                 })
                 .count();
             let num_labs = num_classes - num_tuts;
+            let num_hours = instructor_allocation
+                .iter()
+                .map(|session_id| {
+                    problem.sessions[session_id.raw_index()].duration.hours() as usize
+                })
+                .sum::<usize>();
 
-            let mut add_minmax_cost = |actual, min, max, below, above| {
-                let actual = actual as u8;
+            let mut add_minmax_cost = |actual: usize, min: u8, max: u8, below, above| {
+                let actual = CostCountNum::try_from(actual).unwrap_or(CostCountNum::MAX);
+                let (min, max) = (CostCountNum::from(min), CostCountNum::from(max));
                 if actual < min {
                     costs.add_cost(below, min - actual);
                 }
@@ -140,18 +557,144 @@ impl Solution {
                 Constraint::BelowMinClass,
                 Constraint::AboveMaxClass,
             );
+            add_minmax_cost(
+                num_hours,
+                instructor.class_type_requirement.min_hours,
+                instructor.class_type_requirement.max_hours,
+                Constraint::BelowMinHours,
+                Constraint::AboveMaxHours,
+            );
 
-            for (session_1, session_2) in TwoCombIter::new(instructor_allocation) {
-                if problem.overlap_sharp.is_overlap(session_1, session_2) {
-                    costs.add_cost_1(Constraint::DirectOverlap)
-                } else if problem.cost_config.should_count(Constraint::PaddedOverlap)
-                    && problem.overlap_padded.is_overlap(session_1, session_2)
-                {
-                    costs.add_cost_1(Constraint::PaddedOverlap)
-                } else if problem.cost_config.should_count(Constraint::SameDayOverlap)
-                    && problem.overlap_same_day.is_overlap(session_1, session_2)
+            if problem
+                .cost_config
+                .should_count(Constraint::TooManyDistinctStartTimes)
+            {
+                // allocations are small so a quadratic scan is cheaper than building a set
+                let start_slot = |session_id: &SessionId| {
+                    let session = &problem.sessions[session_id.raw_index()];
+                    (session.day, session.start_time)
+                };
+                let num_start_times = instructor_allocation
+                    .iter()
+                    .enumerate()
+                    .filter(|&(idx, session_id)| {
+                        !instructor_allocation[..idx]
+                            .iter()
+                            .any(|other| start_slot(other) == start_slot(session_id))
+                    })
+                    .count();
+                let max_start_times = instructor.class_type_requirement.max_start_times as usize;
+                if num_start_times > max_start_times {
+                    costs.add_cost(
+                        Constraint::TooManyDistinctStartTimes,
+                        (num_start_times - max_start_times) as CostCountNum,
+                    );
+                }
+            }
+
+            if problem
+                .cost_config
+                .should_count(Constraint::TooManyOnOneDay)
+            {
+                let mut sessions_by_day = [0u8; 5];
+                for session_id in instructor_allocation.iter() {
+                    let day = problem.sessions[session_id.raw_index()].day;
+                    sessions_by_day[day as usize] = sessions_by_day[day as usize].saturating_add(1);
+                }
+
+                let max_per_day = instructor.class_type_requirement.max_per_day;
+                for num_sessions in sessions_by_day {
+                    if num_sessions > max_per_day {
+                        costs.add_cost(Constraint::TooManyOnOneDay, num_sessions - max_per_day);
+                    }
+                }
+            }
+
+            if let Some(max_continuous_hours) = problem.cost_config.max_continuous_hours {
+                if problem
+                    .cost_config
+                    .should_count(Constraint::ContinuousTeachingWithoutBreak)
                 {
-                    costs.add_cost_1(Constraint::SameDayOverlap)
+                    // a bitmask of the hours taught on each day, so back to back (or
+                    // overlapping) sessions merge into one run of teaching
+                    let mut taught_hours_by_day = [0u32; 5];
+                    for session_id in instructor_allocation.iter() {
+                        let session = &problem.sessions[session_id.raw_index()];
+                        let start = session.start_time.as_24_hours();
+                        for hour in start..start + session.duration.hours() {
+                            taught_hours_by_day[session.day as usize] |= 1 << hour;
+                        }
+                    }
+
+                    for mut taught_hours in taught_hours_by_day {
+                        while taught_hours != 0 {
+                            taught_hours >>= taught_hours.trailing_zeros();
+                            let run_length = taught_hours.trailing_ones();
+                            taught_hours = taught_hours.checked_shr(run_length).unwrap_or(0);
+                            if run_length > max_continuous_hours as u32 {
+                                costs.add_cost(
+                                    Constraint::ContinuousTeachingWithoutBreak,
+                                    run_length - max_continuous_hours as u32,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(max_day_span) = problem.cost_config.max_day_span {
+                if problem.cost_config.should_count(Constraint::DaySpanTooLong) {
+                    // the earliest start and latest end on each day
+                    let mut day_bounds: [Option<(u8, u8)>; 5] = [None; 5];
+                    for session_id in instructor_allocation.iter() {
+                        let session = &problem.sessions[session_id.raw_index()];
+                        let start = session.start_time.as_24_hours();
+                        let end = start + session.duration.hours();
+                        let bounds = &mut day_bounds[session.day as usize];
+                        *bounds = Some(match *bounds {
+                            Some((first, last)) => (first.min(start), last.max(end)),
+                            None => (start, end),
+                        });
+                    }
+
+                    for (first, last) in day_bounds.into_iter().flatten() {
+                        let span = last - first;
+                        if span > max_day_span {
+                            costs.add_cost(Constraint::DaySpanTooLong, span - max_day_span);
+                        }
+                    }
+                }
+            }
+
+            for (session_1, session_2) in TwoCombIter::new(instructor_allocation) {
+                match problem.overlap_levels.get(session_1, session_2) {
+                    OverlapLevel::Sharp => {
+                        // scale by how long the clash is, so near-misses are preferred
+                        let hours = problem.overlap_sharp.overlap_hours(session_1, session_2);
+                        costs.add_cost(Constraint::DirectOverlap, hours.max(1))
+                    }
+                    OverlapLevel::Padded => {
+                        // back to back sessions are also on the same day
+                        if problem.cost_config.should_count(Constraint::PaddedOverlap) {
+                            costs.add_cost_1(Constraint::PaddedOverlap)
+                        } else if problem.cost_config.should_count(Constraint::SameDayOverlap) {
+                            costs.add_cost_1(Constraint::SameDayOverlap)
+                        }
+                        if problem
+                            .cost_config
+                            .should_count(Constraint::CrossCampusTransition)
+                            && problem.sessions[session_1.raw_index()]
+                                .changes_location_to(&problem.sessions[session_2.raw_index()])
+                        {
+                            costs.add_cost_1(Constraint::CrossCampusTransition)
+                        }
+                    }
+                    OverlapLevel::SameDay => {
+                        if problem.cost_config.should_count(Constraint::SameDayOverlap) {
+                            costs.add_cost_1(Constraint::SameDayOverlap)
+                        }
+                    }
+                    OverlapLevel::None => {}
                 }
             }
         }
@@ -159,3 +702,95 @@ impl Solution {
         (costs, buffer)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::SyntheticProblem;
+
+    #[test]
+    fn cost_scales_far_above_max_classes() {
+        // more sessions than fit in a u8, so counts of them can't be narrowed
+        let synthetic = SyntheticProblem::generate(0, 200, 4).unwrap();
+        let problem = synthetic.as_problem();
+        let instructor = &problem.instructors[0];
+        let max_total_classes = instructor.class_type_requirement.max_total_classes as usize;
+        assert!(problem.sessions.len() > u8::MAX as usize);
+
+        let mut previous_cost = None;
+        for num_assigned in [100, 256, 300, problem.sessions.len()] {
+            let mut solution = Solution::empty(problem.sessions.len(), false);
+            for assignment in &mut solution.assignment[..num_assigned] {
+                *assignment = Some(instructor.instructor_id);
+            }
+
+            let costs = solution.evaluate(problem, None).0;
+            let above_max_classes = costs
+                .counts()
+                .find(|&(constraint, _)| constraint == Constraint::AboveMaxClass)
+                .map(|(_, count)| count as usize);
+            assert_eq!(above_max_classes, Some(num_assigned - max_total_classes));
+            let overage = (num_assigned - max_total_classes) as CostCountNum;
+            let cost_per_class = problem.cost_config.cost_of(Constraint::AboveMaxClass, 1);
+            assert_eq!(
+                problem
+                    .cost_config
+                    .cost_of(Constraint::AboveMaxClass, overage),
+                cost_per_class.map(|cost| cost * overage as CostValue)
+            );
+
+            let cost = costs.total_cost(problem.cost_config);
+            assert!(
+                cost.is_some(),
+                "{num_assigned} sessions gave an infinite cost"
+            );
+            assert!(
+                previous_cost < cost,
+                "{num_assigned} sessions cost {cost:?}, but fewer cost {previous_cost:?}"
+            );
+            previous_cost = cost;
+        }
+    }
+
+    #[test]
+    fn padded_overlap_falls_back_to_same_day() {
+        let synthetic = SyntheticProblem::generate(0, 60, 4).unwrap();
+        let cost_config: CostConfig = toml::from_str(
+            &include_str!("../costs.example.toml")
+                .replace("padded_overlap = 5", "padded_overlap = 0")
+                .replace("same_day_overlap = 0", "same_day_overlap = 1"),
+        )
+        .unwrap();
+        let problem = Problem {
+            cost_config: &cost_config,
+            ..synthetic.as_problem()
+        };
+
+        let (session_1, session_2) = TwoCombIter::new(
+            &problem
+                .sessions
+                .iter()
+                .map(|session| session.session_id)
+                .collect::<Vec<_>>(),
+        )
+        .find(|&(session_1, session_2)| {
+            problem.overlap_levels.get(session_1, session_2) == OverlapLevel::Padded
+        })
+        .expect("the synthetic problem should have back to back sessions");
+
+        let mut solution = Solution::empty(problem.sessions.len(), false);
+        let instructor_id = problem.instructors[0].instructor_id;
+        solution.assignment[session_1.raw_index()] = Some(instructor_id);
+        solution.assignment[session_2.raw_index()] = Some(instructor_id);
+
+        let costs = solution.evaluate(problem, None).0;
+        let count_of = |constraint| {
+            costs
+                .counts()
+                .find(|&(counted, _)| counted == constraint)
+                .map_or(0, |(_, count)| count)
+        };
+        assert_eq!(count_of(Constraint::PaddedOverlap), 0);
+        assert_eq!(count_of(Constraint::SameDayOverlap), 1);
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use bit_set::BitSet;
+
+use crate::{
+    instructor::{Instructor, InstructorId},
+    session::{Session, SessionId},
+    tsv::Tsv,
+    utils::matches_glob_list,
+};
+
+// Which sessions each instructor taught the class of in a previous term. People in
+// the history who aren't instructors this term are ignored.
+#[derive(Default)]
+pub struct TeachingHistory {
+    num_instructors: usize,
+    taught: BitSet,
+    has_returning_tutor: BitSet,
+    pub num_unknown_zids: usize,
+}
+
+impl TeachingHistory {
+    pub fn from_tsv(tsv: &Tsv, instructors: &[Instructor], sessions: &[Session]) -> Result<Self> {
+        let num_instructors = instructors.len();
+        let mut taught = BitSet::with_capacity(num_instructors * sessions.len());
+        let mut has_returning_tutor = BitSet::with_capacity(sessions.len());
+        let mut num_unknown_zids = 0;
+
+        for row in tsv {
+            let zid = row.get("zid")?.trim();
+            let class_pattern = row.get("class")?;
+
+            let Some(instructor) = instructors.iter().find(|instructor| instructor.zid == zid)
+            else {
+                num_unknown_zids += 1;
+                continue;
+            };
+
+            for session in sessions {
+                if matches_glob_list(class_pattern, &session.class_name) {
+                    taught.insert(
+                        session.session_id.raw_index() * num_instructors
+                            + instructor.instructor_id.raw_index(),
+                    );
+                    has_returning_tutor.insert(session.session_id.raw_index());
+                }
+            }
+        }
+
+        Ok(TeachingHistory {
+            num_instructors,
+            taught,
+            has_returning_tutor,
+            num_unknown_zids,
+        })
+    }
+
+    pub fn num_sessions_with_history(&self) -> usize {
+        self.has_returning_tutor.len()
+    }
+
+    // Whether some instructor this term has taught the session's class before
+    pub fn has_returning_tutor(&self, session: SessionId) -> bool {
+        self.has_returning_tutor.contains(session.raw_index())
+    }
+
+    pub fn has_taught(&self, session: SessionId, instructor: InstructorId) -> bool {
+        self.taught
+            .contains(session.raw_index() * self.num_instructors + instructor.raw_index())
+    }
+}
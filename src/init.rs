@@ -0,0 +1,60 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::costs::costs_toml_template;
+
+// Only the required columns, the optional ones are listed when the templates are written
+const INSTRUCTORS_TSV_HEADERS: [&str; 6] = ["name", "zid", "minT", "maxT", "minA", "maxA"];
+const INSTRUCTORS_TSV_OPTIONAL_HEADERS: [&str; 11] = [
+    "minC",
+    "maxC",
+    "min_hours",
+    "max_hours",
+    "max_start_times",
+    "max_per_day",
+    "pinned_count",
+    "senior tutor",
+    "new tutor",
+    "reserve",
+    "ignore",
+];
+const OVERRIDES_TSV_HEADERS: [&str; 6] = ["name", "zid", "class", "type", "override", "mode"];
+const INITIAL_TSV_HEADERS: [&str; 5] = ["class", "type", "zid", "name", "weight"];
+
+// Writes template input files into `dir` for setting up a new term, without
+// overwriting any which already exist
+pub fn write_templates(dir: &Path) -> Result<()> {
+    let templates = [
+        ("costs.toml", costs_toml_template()),
+        ("instructors.tsv", INSTRUCTORS_TSV_HEADERS.join("\t") + "\n"),
+        ("overrides.tsv", OVERRIDES_TSV_HEADERS.join("\t") + "\n"),
+        ("initial.tsv", INITIAL_TSV_HEADERS.join("\t") + "\n"),
+    ];
+
+    let existing = templates
+        .iter()
+        .map(|(filename, _)| dir.join(filename))
+        .filter(|path| path.exists())
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+    if !existing.is_empty() {
+        bail!("not overwriting existing {}", existing.join(", "));
+    }
+
+    fs::create_dir_all(dir).with_context(|| anyhow!("failed to create {}", dir.display()))?;
+    for (filename, contents) in templates {
+        let path = dir.join(filename);
+        fs::write(&path, contents)
+            .with_context(|| anyhow!("failed to write {}", path.display()))?;
+        println!("Wrote {}", path.display());
+    }
+
+    println!(
+        "instructors.tsv can also have the optional columns: {}",
+        INSTRUCTORS_TSV_OPTIONAL_HEADERS.join(", ")
+    );
+    println!("classes.tsv still needs to be added before solving");
+
+    Ok(())
+}
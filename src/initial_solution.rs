@@ -1,11 +1,14 @@
-use std::path::Path;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
 use anyhow::{anyhow, bail, Context, Result};
 use itertools::Itertools;
 
 use crate::{
     evaluator::Solution,
-    instructor::Instructor,
+    instructor::{Instructor, ANONYMIZATION_KEY_FILENAME},
     session::{Session, SessionType},
     tsv::Tsv,
 };
@@ -14,38 +17,85 @@ pub fn get_initial_solution(
     initial_tsv_path: &Path,
     sessions: &[Session],
     instructors: &[Instructor],
-) -> Result<Solution> {
+    filtered_out_classes: &HashSet<String>,
+) -> Result<(Solution, Vec<u32>)> {
+    // how much each session costs to change from the initial solution, as a
+    // multiplier of mismatched_initial_solution
+    let mut mismatch_weights = vec![1; sessions.len()];
+
     if !initial_tsv_path.is_file() {
         println!("Using empty initial solution");
-        Ok(Solution::empty(sessions.len(), false))
+        Ok((Solution::empty(sessions.len(), false), mismatch_weights))
     } else {
         let mut assignment = vec![None; sessions.len()];
 
+        // a solution written with --anonymize has the key next to it, which is used
+        // to map the pseudonyms back to the real instructors
+        let key_path = initial_tsv_path.with_file_name(ANONYMIZATION_KEY_FILENAME);
+        let pseudonyms = if key_path.is_file() {
+            println!(
+                "De-anonymizing {} using {}",
+                initial_tsv_path.display(),
+                key_path.display()
+            );
+            Instructor::read_anonymization_key(&Tsv::read_from_path(&key_path)?)?
+        } else {
+            HashMap::new()
+        };
+
         for row in &Tsv::read_from_path(initial_tsv_path)? {
             let class_name = row.get("class")?;
+            if filtered_out_classes.contains(class_name) {
+                println!(
+                    "Note: ignoring initial solution for {class_name} since it was filtered out"
+                );
+                continue;
+            }
+
             let class_type = match row.get("type")? {
                 "tut+lab" => SessionType::TutLab,
                 "lab" => SessionType::LabAssist,
                 bad_type => bail!("bad session type {:?} for {class_name}", bad_type),
             };
-            let instructor_zid = row.get("zid")?;
-            let instructor_name = row.get("name")?;
+            let (instructor_zid, instructor_name) = match pseudonyms.get(row.get("zid")?) {
+                Some((zid, name)) => (zid.as_str(), name.as_str()),
+                None => (row.get("zid")?, row.get("name")?),
+            };
 
             if instructor_zid == "-" {
                 continue;
             };
 
-            let (instructor,) = instructors
-                .iter()
-                .filter(|instructor| instructor.zid == instructor_zid)
-                .collect_tuple()
-                .with_context(|| {
-                    anyhow!("cannot find instructor {instructor_zid} for class {class_name}")
-                })?;
+            let instructor = if instructor_zid.trim().is_empty() {
+                // no zid given, so fall back to finding the instructor by name
+                let matching = instructors
+                    .iter()
+                    .filter(|instructor| instructor.name.trim() == instructor_name.trim())
+                    .collect::<Vec<_>>();
 
-            if instructor.name != instructor_name {
-                println!("Warning: initial solution for class {class_name} has {instructor_zid}'s name as \"{instructor_name}\" but it should be \"{}\"", instructor.name);
-            }
+                match matching.as_slice() {
+                    [instructor] => *instructor,
+                    [] => bail!("cannot find instructor named \"{instructor_name}\" for class {class_name}"),
+                    _ => bail!(
+                        "instructor name \"{instructor_name}\" for class {class_name} is ambiguous (could be any of {}), use a zid instead",
+                        matching.iter().map(|instructor| &instructor.zid).join(", ")
+                    ),
+                }
+            } else {
+                let (instructor,) = instructors
+                    .iter()
+                    .filter(|instructor| instructor.zid == instructor_zid)
+                    .collect_tuple()
+                    .with_context(|| {
+                        anyhow!("cannot find instructor {instructor_zid} for class {class_name}")
+                    })?;
+
+                if instructor.name != instructor_name {
+                    println!("Warning: initial solution for class {class_name} has {instructor_zid}'s name as \"{instructor_name}\" but it should be \"{}\"", instructor.name);
+                }
+
+                instructor
+            };
 
             let instructor_id = instructor.instructor_id;
 
@@ -63,8 +113,20 @@ pub fn get_initial_solution(
             }
 
             assignment[session_id.raw_index()] = Some(instructor_id);
+
+            // the weight column is optional, and defaults to 1
+            mismatch_weights[session_id.raw_index()] = match row.get("weight") {
+                Err(_) => 1,
+                Ok(weight) if weight.trim().is_empty() => 1,
+                Ok(weight) => weight.trim().parse().with_context(|| {
+                    anyhow!("bad mismatch weight {weight:?} for class {class_name}")
+                })?,
+            };
         }
 
-        Ok(Solution::new(assignment.into_boxed_slice()))
+        Ok((
+            Solution::new(assignment.into_boxed_slice()),
+            mismatch_weights,
+        ))
     }
 }
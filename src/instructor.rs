@@ -1,11 +1,17 @@
-use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context, Result};
+use itertools::Itertools;
 
 use crate::{
     tsv::{Tsv, TsvRow},
     utils::parse_bool_input,
 };
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+// Written next to the solution in each output directory when anonymizing
+pub const ANONYMIZATION_KEY_FILENAME: &str = "anonymization_key.tsv";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct InstructorId(u16);
 
 impl InstructorId {
@@ -18,7 +24,7 @@ impl InstructorId {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Instructor {
     pub instructor_id: InstructorId,
     pub name: String,
@@ -26,9 +32,12 @@ pub struct Instructor {
     pub class_type_requirement: ClassTypeRequirement,
 
     pub seniority: Option<TutorSeniority>,
+
+    // reserve instructors are only rostered if the core staff can't cover everything
+    pub is_reserve: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct ClassTypeRequirement {
     pub min_tutes: u8,
     pub max_tutes: u8,
@@ -36,9 +45,16 @@ pub struct ClassTypeRequirement {
     pub max_lab_assists: u8,
     pub min_total_classes: u8,
     pub max_total_classes: u8,
+    pub min_hours: u8,
+    pub max_hours: u8,
+    pub max_start_times: u8,
+    pub max_per_day: u8,
+    // from the optional `pinned_count` column, which sets both minC and maxC so the
+    // number of classes is fixed even though which classes can change
+    pub pinned_count: Option<u8>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct TutorSeniority {
     pub is_senior_tutor: bool,
     pub is_new_tutor: bool,
@@ -68,6 +84,11 @@ impl TryFrom<TsvRow<'_>> for Option<Instructor> {
         let seniority = row
             .try_into()
             .with_context(|| anyhow!("could not parse seniority status for {zid} ({name})"))?;
+        let is_reserve = match row.get("reserve") {
+            Ok(reserve) if !reserve.trim().is_empty() => parse_bool_input(reserve.trim())
+                .with_context(|| anyhow!("bad reserve for {zid} ({name})"))?,
+            _ => false,
+        };
 
         Ok(Some(Instructor {
             instructor_id,
@@ -75,18 +96,116 @@ impl TryFrom<TsvRow<'_>> for Option<Instructor> {
             zid,
             class_type_requirement,
             seniority,
+            is_reserve,
         }))
     }
 }
 
 impl Instructor {
-    pub fn vec_from_tsv(tsv: &Tsv) -> Result<Vec<Instructor>> {
-        Ok(tsv
+    // Replaces every instructor's name and zid with a pseudonym so outputs can be shared,
+    // returning a TSV which maps the pseudonyms back to the real identities. Pseudonyms
+    // are numbered in zid order so they're stable between runs.
+    pub fn anonymize(instructors: &mut [Instructor]) -> Result<String> {
+        let mut rows = Vec::new();
+
+        for (number, instructor) in instructors
+            .iter_mut()
+            .sorted_by(|a, b| a.zid.cmp(&b.zid))
+            .enumerate()
+        {
+            let pseudonym_zid = format!("tutor{}", number + 1);
+            let pseudonym = format!("Tutor {}", number + 1);
+            rows.push(vec![
+                pseudonym_zid.clone(),
+                pseudonym.clone(),
+                std::mem::replace(&mut instructor.zid, pseudonym_zid),
+                std::mem::replace(&mut instructor.name, pseudonym),
+            ]);
+        }
+
+        Ok(Tsv::from_rows(
+            ANONYMIZATION_KEY_FILENAME,
+            &["pseudonym_zid", "pseudonym", "zid", "name"],
+            rows,
+        )?
+        .to_string())
+    }
+
+    // Reads a key written by `anonymize`, mapping each pseudonym zid to the real zid
+    // and name
+    pub fn read_anonymization_key(key_tsv: &Tsv) -> Result<HashMap<String, (String, String)>> {
+        key_tsv
             .into_iter()
-            .map(Option::<Instructor>::try_from)
-            .collect::<Result<Vec<_>>>()?
+            .map(|row| {
+                Ok((
+                    row.get("pseudonym_zid")?.into(),
+                    (row.get("zid")?.into(), row.get("name")?.into()),
+                ))
+            })
+            .collect()
+    }
+
+    // Merges the instructors from several TSVs, where an instructor can appear
+    // in more than one file as long as the rows agree. A zid repeated within one
+    // file or with conflicting rows is an error, unless `allow_dupes` is set in
+    // which case it's just a warning and the first row is kept.
+    pub fn vec_from_tsvs(tsvs: &[Tsv], allow_dupes: bool) -> Result<Vec<Instructor>> {
+        let mut instructors: Vec<Instructor> = Vec::new();
+        // the file and line each instructor was first loaded from
+        let mut sources: Vec<(&str, usize)> = Vec::new();
+
+        for tsv in tsvs {
+            // the line each zid first appears on in this file, since a zid can be
+            // repeated within a later file after matching a row of an earlier one
+            let mut lines_in_file: HashMap<String, usize> = HashMap::new();
+
+            for row in tsv {
+                let Some(instructor) = Option::<Instructor>::try_from(row)? else {
+                    continue;
+                };
+
+                let repeated_line = lines_in_file.get(&instructor.zid).copied();
+                if repeated_line.is_none() {
+                    lines_in_file.insert(instructor.zid.clone(), row.line_number());
+                }
+
+                let Some(existing_index) = instructors
+                    .iter()
+                    .position(|existing| existing.zid == instructor.zid)
+                else {
+                    instructors.push(instructor);
+                    sources.push((tsv.path(), row.line_number()));
+                    continue;
+                };
+
+                let (existing_path, existing_line) = sources[existing_index];
+                let (problem, existing_line, existing_path) = if let Some(line) = repeated_line {
+                    ("is repeated", line, tsv.path())
+                } else if instructors[existing_index] != instructor {
+                    (
+                        "conflicts with an earlier row",
+                        existing_line,
+                        existing_path,
+                    )
+                } else {
+                    continue;
+                };
+                let message = format!(
+                    "instructor {} ({}) on line {} of {} {problem} for the same zid on line {existing_line} of {existing_path}",
+                    instructor.zid,
+                    instructor.name,
+                    row.line_number(),
+                    tsv.path()
+                );
+                if !allow_dupes {
+                    bail!("{message} (pass --allow-dupes to keep the first row instead)");
+                }
+                println!("Warning: {message}, keeping the first row");
+            }
+        }
+
+        Ok(instructors
             .into_iter()
-            .flatten()
             .enumerate()
             .map(|(idx, mut instructor)| {
                 instructor.instructor_id = InstructorId(idx as _);
@@ -118,8 +237,26 @@ impl TryFrom<TsvRow<'_>> for ClassTypeRequirement {
         let min_lab_assists = get_requirement("minA")?;
         let max_lab_assists = get_requirement("maxA")?;
 
-        let min_total_classes = get_requirement_or_default("minC", min_tutes + min_lab_assists)?;
-        let max_total_classes = get_requirement_or_default("maxC", max_tutes + max_lab_assists)?;
+        let mut min_total_classes =
+            get_requirement_or_default("minC", min_tutes + min_lab_assists)?;
+        let mut max_total_classes =
+            get_requirement_or_default("maxC", max_tutes + max_lab_assists)?;
+
+        let pinned_count = match row.get("pinned_count") {
+            Err(_) | Ok("" | "-") => None,
+            Ok(val) => Some(val.parse::<u8>().with_context(|| {
+                anyhow!("could not parse value of field pinned_count as number")
+            })?),
+        };
+        if let Some(pinned_count) = pinned_count {
+            min_total_classes = pinned_count;
+            max_total_classes = pinned_count;
+        }
+
+        let min_hours = get_requirement_or_default("min_hours", 0)?;
+        let max_hours = get_requirement_or_default("max_hours", u8::MAX)?;
+        let max_start_times = get_requirement_or_default("max_start_times", u8::MAX)?;
+        let max_per_day = get_requirement_or_default("max_per_day", u8::MAX)?;
 
         Ok(ClassTypeRequirement {
             min_tutes,
@@ -128,6 +265,11 @@ impl TryFrom<TsvRow<'_>> for ClassTypeRequirement {
             max_lab_assists,
             min_total_classes,
             max_total_classes,
+            min_hours,
+            max_hours,
+            max_start_times,
+            max_per_day,
+            pinned_count,
         })
     }
 }
@@ -155,3 +297,41 @@ impl TryFrom<TsvRow<'_>> for Option<TutorSeniority> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anonymization_key_maps_pseudonyms_back() -> Result<()> {
+        let tsv = Tsv::try_from_str(
+            "instructors.tsv",
+            "name\tzid\tminT\tmaxT\tminA\tmaxA\n\
+             Bob\tz2\t0\t1\t0\t1\n\
+             Alice\tz1\t0\t1\t0\t1\n",
+        )?;
+        let mut instructors = Instructor::vec_from_tsvs(&[tsv], false)?;
+
+        let key = Instructor::anonymize(&mut instructors)?;
+        assert_eq!(instructors[0].zid, "tutor2");
+        assert_eq!(instructors[1].name, "Tutor 1");
+
+        let key_tsv = Tsv::try_from_str(ANONYMIZATION_KEY_FILENAME, &key)?;
+        let pseudonyms = Instructor::read_anonymization_key(&key_tsv)?;
+        assert_eq!(pseudonyms["tutor1"], ("z1".into(), "Alice".into()));
+        assert_eq!(pseudonyms["tutor2"], ("z2".into(), "Bob".into()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn repeats_within_a_later_file_are_duplicates() -> Result<()> {
+        let header = "name\tzid\tminT\tmaxT\tminA\tmaxA\n";
+        let row = "Alice\tz1\t0\t1\t0\t1\n";
+        let first = Tsv::try_from_str("instructors.tsv", &format!("{header}{row}"))?;
+        let second = Tsv::try_from_str("instructors2.tsv", &format!("{header}{row}{row}"))?;
+
+        assert!(Instructor::vec_from_tsvs(&[first, second], false).is_err());
+        Ok(())
+    }
+}
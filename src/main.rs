@@ -1,32 +1,63 @@
-use std::{path::PathBuf, sync::Mutex};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
 
-use anyhow::{Context, Result};
-use availabilities::AvailabilityMatrix;
+use anyhow::{anyhow, bail, Context, Result};
+use availabilities::{AvailabilityMatrix, HourCombinePolicy};
+use benchmark::run_benchmark;
 use checks::check_problem;
 use clap::Parser;
+use class_prefs::ClassPreferences;
 use classes::{Class, Mode};
-use costs::CostConfig;
+use costs::{CostConfig, CostValue};
+use enrolments::Enrolments;
 use evaluator::Problem;
+use history::TeachingHistory;
+use init::write_templates;
 use initial_solution::get_initial_solution;
-use instructor::Instructor;
-use overrides::apply_overrides;
+use instructor::{Instructor, ANONYMIZATION_KEY_FILENAME};
+use itertools::Itertools;
+use overrides::apply_override_files;
 use scoped_threadpool::Pool;
-use session::{classes_to_sessions, OverlapMatrix, OverlapRequirement};
-use solution_output::{instructor_stats_from_solution, output_solution};
-use solver::{solve_once, SolverSeed};
-use talloc::TallocApps;
+use selftest::run_selftest;
+use session::{
+    classes_to_sessions, ConcurrentGroups, ContinuityGroups, DayVariants, LabLeads, OverlapLevels,
+    OverlapMatrix, OverlapRequirement,
+};
+use solution_output::{
+    instructor_stats_from_solution, output_solution, preference_summary, OutputOptions, RunInfo,
+    DEFAULT_EMAIL_TEMPLATE,
+};
+use solver::{
+    robustness_report, sample_schedule, solve_once, summarise_final_costs,
+    warn_about_short_schedule, SolverOptions, SolverSeed,
+};
+use talloc::{TallocApps, TermCombinePolicy};
 use tsv::Tsv;
-use utils::indent_lines;
+use utils::{indent_lines, matches_glob_list};
 
 mod availabilities;
+mod benchmark;
 mod checks;
+mod class_prefs;
 mod classes;
 mod costs;
+mod enrolments;
 mod evaluator;
+mod history;
+mod init;
 mod initial_solution;
 mod instructor;
 mod mutation;
 mod overrides;
+mod rng;
+mod selftest;
 mod session;
 mod solution_output;
 mod solver;
@@ -39,33 +70,173 @@ struct Args {
     config_dir: PathBuf,
     #[arg(long)]
     ignore_no_talloc: bool,
-    #[arg(long, default_value_t = 1)]
-    cpus: u32,
+    #[arg(long)]
+    allow_dupes: bool,
+    #[arg(long)]
+    no_download: bool,
+    #[arg(long, value_delimiter = ',')]
+    talloc_terms: Vec<String>,
+    #[arg(long, value_enum, default_value_t = TermCombinePolicy::Min)]
+    combine_terms: TermCombinePolicy,
+    #[arg(long, value_enum, default_value_t = HourCombinePolicy::Min)]
+    hour_policy: HourCombinePolicy,
+    #[arg(long)]
+    cpus: Option<u32>,
     #[arg(long)]
     initial_costs: bool,
     #[arg(long)]
     start_seed: Option<u64>,
     #[arg(long, default_value_t = 20)]
     total_attempts: u64,
+    #[arg(long)]
+    seed_list: Option<PathBuf>,
     #[arg(long, default_value_t = 75_000_000)]
     num_rounds: u64,
+    #[arg(long)]
+    accepted_moves: Option<u64>,
+    #[arg(long)]
+    polish: bool,
+    #[arg(long)]
+    never_unassign: bool,
+    #[arg(long)]
+    reheat_after: Option<u64>,
+    #[arg(long, default_value_t = 0.5)]
+    reheat_factor: f64,
+    #[arg(long, default_value_t = 1)]
+    restarts_per_seed: u64,
+    #[arg(long, default_value_t = 0.0)]
+    guided_fraction: f64,
+    #[arg(long)]
+    stable_rng: bool,
+    #[arg(long)]
+    debug_mutations: bool,
+    #[arg(long, default_value_t = 1.0)]
+    boltzmann_k: f32,
+    #[arg(long)]
+    deterministic_ties: bool,
+    #[arg(long)]
+    sample_schedule: bool,
+    #[arg(long)]
+    start_time_grid: Option<u16>,
+    #[arg(long)]
+    assign_only: Option<String>,
+    #[arg(long)]
+    overlap_graph: Option<PathBuf>,
+    #[arg(long)]
+    min_improvement_over: Option<CostValue>,
+    #[arg(long)]
+    skip_bad_classes: bool,
+    #[arg(long)]
+    only_classes: Option<String>,
+    #[arg(long)]
+    cost_profile: Option<String>,
+    #[arg(long)]
+    neutral_possible: bool,
+    #[arg(long)]
+    incremental: Option<PathBuf>,
+    #[arg(long)]
+    benchmark: bool,
+    #[arg(long)]
+    selftest: bool,
+    #[arg(long)]
+    init: bool,
+    #[arg(long)]
+    stats_only: Option<PathBuf>,
+    #[arg(long)]
+    quiet_solver: bool,
+    #[arg(long)]
+    compare_seeds: bool,
+    #[arg(long)]
+    robust: bool,
+    #[arg(long)]
+    anonymize: bool,
+    #[arg(long)]
+    full_problem_dump: bool,
+    #[arg(long)]
+    max_output_dirs: Option<usize>,
+    #[arg(long)]
+    email_template: Option<PathBuf>,
 }
 
 impl Args {
     fn get_file_path(&self, filename: &str) -> PathBuf {
         self.config_dir.join(filename)
     }
+
+    // All files in the config dir named like `{prefix}*{suffix}`, sorted by name
+    fn get_matching_file_paths(&self, prefix: &str, suffix: &str) -> Result<Vec<PathBuf>> {
+        let mut paths = fs::read_dir(&self.config_dir)
+            .with_context(|| anyhow!("failed to list {}", self.config_dir.display()))?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<Vec<_>>>()?;
+
+        paths.retain(|path| {
+            path.is_file()
+                && path.file_name().is_some_and(|name| {
+                    let name = name.to_string_lossy();
+                    name.starts_with(prefix) && name.ends_with(suffix)
+                })
+        });
+        paths.sort();
+
+        Ok(paths)
+    }
 }
 
 fn main_impl() -> Result<()> {
     let args = Args::parse();
+    let run_info = RunInfo::new(&args.config_dir, args.stable_rng);
 
-    let instructors = Instructor::vec_from_tsv(&Tsv::read_from_path(
-        &args.get_file_path("instructors.tsv"),
-    )?)?;
-    println!("Loaded {} instructors", instructors.len());
+    if args.benchmark {
+        return run_benchmark(&args.get_file_path("benchmark_baseline.json"));
+    }
+    if args.selftest {
+        return run_selftest();
+    }
+    if args.init {
+        return write_templates(&args.config_dir);
+    }
+
+    let instructor_tsv_paths = args.get_matching_file_paths("instructors", ".tsv")?;
+    if instructor_tsv_paths.is_empty() {
+        bail!("no instructors*.tsv files in {}", args.config_dir.display());
+    }
+    let instructors = Instructor::vec_from_tsvs(
+        &instructor_tsv_paths
+            .iter()
+            .map(|path| Tsv::read_from_path(path))
+            .collect::<Result<Vec<_>>>()?,
+        args.allow_dupes,
+    )?;
+    println!(
+        "Loaded {} instructors from {} file(s)",
+        instructors.len(),
+        instructor_tsv_paths.len()
+    );
+
+    let mut classes = Class::vec_from_tsv(
+        &Tsv::read_from_path(&args.get_file_path("classes.tsv"))?,
+        args.start_time_grid,
+        args.skip_bad_classes,
+    )?;
+
+    let mut filtered_out_classes = HashSet::new();
+    if let Some(only_classes) = &args.only_classes {
+        let (kept_classes, removed_classes): (Vec<_>, Vec<_>) = classes
+            .into_iter()
+            .partition(|class| matches_glob_list(only_classes, &class.name));
+        println!(
+            "Only solving for classes matching {only_classes:?} ({} filtered out)",
+            removed_classes.len()
+        );
+
+        classes = kept_classes;
+        filtered_out_classes = removed_classes
+            .into_iter()
+            .map(|class| class.name)
+            .collect();
+    }
 
-    let classes = Class::vec_from_tsv(&Tsv::read_from_path(&args.get_file_path("classes.tsv"))?)?;
     println!(
         "Loaded {} classes ({} face to face, {} online)",
         classes.len(),
@@ -79,17 +250,56 @@ fn main_impl() -> Result<()> {
             .count()
     );
 
-    let sessions = classes_to_sessions(&classes);
+    let sessions = classes_to_sessions(&classes)?;
 
     let overlaps_sharp = OverlapMatrix::from_sessions(&sessions, OverlapRequirement::Sharp);
     let overlaps_padded = OverlapMatrix::from_sessions(&sessions, OverlapRequirement::WithPadding);
     let overlaps_same_day = OverlapMatrix::from_sessions(&sessions, OverlapRequirement::SameDay);
+    let overlap_levels = OverlapLevels::from_matrices(
+        &sessions,
+        &overlaps_sharp,
+        &overlaps_padded,
+        &overlaps_same_day,
+    );
+    if let Some(overlap_graph_path) = &args.overlap_graph {
+        fs::write(overlap_graph_path, overlap_levels.to_dot(&sessions)).with_context(|| {
+            anyhow!(
+                "failed to write overlap graph to {}",
+                overlap_graph_path.display()
+            )
+        })?;
+        println!("Wrote overlap graph to {}", overlap_graph_path.display());
+    }
+    let lab_leads = LabLeads::from_sessions(&sessions);
+    let continuity_groups = ContinuityGroups::from_classes(&classes, &sessions);
+    let concurrent_groups = ConcurrentGroups::from_sessions(&sessions, &overlaps_sharp);
+    let day_variants = DayVariants::from_classes(&classes, &sessions);
 
-    let applications = TallocApps::fetch(
-        &args.get_file_path("talloc_cache.json"),
-        args.ignore_no_talloc,
-    )?;
+    // a survey of availabilities replaces talloc for anyone without access to it
+    let survey_tsv_path = args.get_file_path("availability_survey.tsv");
+    let applications = if survey_tsv_path.exists() {
+        println!(
+            "Using availabilities from {} instead of talloc",
+            survey_tsv_path.display()
+        );
+        TallocApps::from_survey_tsv(
+            &Tsv::read_from_path(&survey_tsv_path)?,
+            args.combine_terms,
+            args.ignore_no_talloc,
+        )
+        .context("Failed to process availability survey")?
+    } else {
+        TallocApps::fetch(
+            &args.get_file_path("talloc_cache.json"),
+            &args.talloc_terms,
+            args.combine_terms,
+            args.ignore_no_talloc,
+            !args.no_download,
+        )?
+    };
 
+    // these have already been reported, so check_problem doesn't warn about them again
+    let mut defaulted_instructors = HashSet::new();
     for instructor in &instructors {
         if applications
             .get_application(&instructor.zid)
@@ -98,45 +308,226 @@ fn main_impl() -> Result<()> {
             println!(
                 "Using 'all impossible' default application for {} ({})",
                 instructor.zid, instructor.name
-            )
+            );
+            defaulted_instructors.insert(instructor.instructor_id);
         }
     }
 
-    let mut availabilities = AvailabilityMatrix::build(&instructors, &sessions, &applications)?;
+    let mut availabilities =
+        AvailabilityMatrix::build(&instructors, &sessions, &applications, args.hour_policy)?;
 
     // the applications are pretty big, so free up some memory now
     drop(applications);
 
-    let overrides_tsv_path = args.get_file_path("overrides.tsv");
-    if overrides_tsv_path.exists() {
-        apply_overrides(
-            &Tsv::read_from_path(&overrides_tsv_path)?,
-            &mut availabilities,
+    // the matching paths are sorted, so overrides are applied in alphabetical order
+    // of filename and the last override of a session for an instructor wins
+    let overrides_tsvs = args
+        .get_matching_file_paths("overrides", ".tsv")?
+        .iter()
+        .map(|path| Tsv::read_from_path(path))
+        .collect::<Result<Vec<_>>>()?;
+    apply_override_files(
+        &overrides_tsvs,
+        &mut availabilities,
+        &instructors,
+        &sessions,
+    )?;
+
+    // costs.toml can override an optional shared base policy in costs.base.toml
+    let mut cost_paths = vec![args.get_file_path("costs.toml")];
+    let base_costs_path = args.get_file_path("costs.base.toml");
+    if base_costs_path.exists() {
+        println!("Using base costs from {}", base_costs_path.display());
+        cost_paths.insert(0, base_costs_path);
+    }
+    let mut cost_config = CostConfig::read_from_tomls(&cost_paths, args.cost_profile.as_deref())?;
+    if args.neutral_possible {
+        cost_config.make_possible_neutral();
+    }
+    cost_config.warn_about_flat_assignment_costs();
+    // every pair of session and instructor is a generous bound on how often a cost is counted
+    let max_cost_count = sessions.len() * instructors.len().max(1);
+    cost_config.warn_about_effectively_hard_costs(max_cost_count.try_into().unwrap_or(u32::MAX));
+    let contiguous_groups = cost_config.contiguous_group_sessions(&sessions);
+
+    let class_prefs_tsv_path = args.get_file_path("class_prefs.tsv");
+    let class_preferences = if class_prefs_tsv_path.exists() {
+        let class_preferences = ClassPreferences::from_tsv(
+            &Tsv::read_from_path(&class_prefs_tsv_path)?,
             &instructors,
             &sessions,
         )
-        .context("Failed to process overrides")?;
+        .context("Failed to process class preferences")?;
+        println!(
+            "Loaded {} course team class preferences",
+            class_preferences.preferences.len()
+        );
+        class_preferences
     } else {
-        println!("No overrides applied");
+        ClassPreferences::default()
+    };
+
+    // in incremental mode an existing solution is used as the initial
+    // solution, and all of its assignments are locked in place
+    let initial_tsv_path = args
+        .incremental
+        .clone()
+        .unwrap_or_else(|| args.get_file_path("initial.tsv"));
+    if args.incremental.is_some() && !initial_tsv_path.is_file() {
+        bail!(
+            "incremental solution {} doesn't exist",
+            initial_tsv_path.display()
+        );
     }
 
-    let cost_config = CostConfig::read_from_toml(&args.get_file_path("costs.toml"))?;
+    let student_in_tsv_path = args.get_file_path("student_in.tsv");
+    let enrolments = if student_in_tsv_path.exists() {
+        let enrolments = Enrolments::from_tsv(
+            &Tsv::read_from_path(&student_in_tsv_path)?,
+            &instructors,
+            &sessions,
+        )
+        .context("Failed to process student enrolments")?;
+        println!(
+            "Loaded {} sessions which instructors are enrolled in",
+            enrolments.num_enrolments()
+        );
+        enrolments
+    } else {
+        Enrolments::default()
+    };
 
-    let initial_solution =
-        get_initial_solution(&args.get_file_path("initial.tsv"), &sessions, &instructors)
-            .context("Failed to process initial solution\n")?;
+    let history_tsv_path = args.get_file_path("history.tsv");
+    let history = if history_tsv_path.exists() {
+        let history = TeachingHistory::from_tsv(
+            &Tsv::read_from_path(&history_tsv_path)?,
+            &instructors,
+            &sessions,
+        )
+        .context("Failed to process teaching history")?;
+        println!(
+            "Loaded teaching history covering {} sessions ({} rows for people who aren't instructors this term)",
+            history.num_sessions_with_history(),
+            history.num_unknown_zids
+        );
+        history
+    } else {
+        TeachingHistory::default()
+    };
+
+    let (initial_solution, mismatch_weights) = get_initial_solution(
+        &initial_tsv_path,
+        &sessions,
+        &instructors,
+        &filtered_out_classes,
+    )
+    .context("Failed to process initial solution\n")?;
+
+    if args.never_unassign {
+        let inactive_sessions = day_variants.inactive_sessions(&initial_solution.assignment);
+        let num_unassigned = initial_solution
+            .assignment
+            .iter()
+            .zip(inactive_sessions)
+            .filter(|&(assignment, inactive)| assignment.is_none() && !inactive)
+            .count();
+        if num_unassigned > 0 {
+            bail!("--never-unassign needs a complete initial solution, but {num_unassigned} sessions are unassigned");
+        }
+    }
+
+    // when focusing on one instructor everyone else's sessions stay as they are
+    let focus_instructor = match &args.assign_only {
+        Some(zid) => {
+            let instructor = instructors
+                .iter()
+                .find(|instructor| instructor.zid == *zid)
+                .with_context(|| anyhow!("--assign-only instructor {zid} doesn't exist"))?;
+            if args.never_unassign {
+                bail!("--assign-only can't move {zid}'s sessions with --never-unassign");
+            }
+            Some(instructor.instructor_id)
+        }
+        None => None,
+    };
+
+    // sessions with the fewest feasible instructors come first, so they're
+    // considered first when polishing and favoured by guided mutations
+    let unlocked_sessions = sessions
+        .iter()
+        .map(|session| session.session_id)
+        .filter(|session_id| {
+            args.incremental.is_none()
+                || initial_solution.assignment[session_id.raw_index()].is_none()
+        })
+        .filter(|session_id| {
+            let initial_assignment = initial_solution.assignment[session_id.raw_index()];
+            focus_instructor.is_none()
+                || initial_assignment
+                    .is_none_or(|instructor_id| Some(instructor_id) == focus_instructor)
+        })
+        .sorted_by_key(|&session_id| {
+            availabilities.num_feasible_instructors(session_id, &instructors)
+        })
+        .collect::<Vec<_>>();
+    if args.incremental.is_some() || focus_instructor.is_some() {
+        println!(
+            "Locked {} existing assignments, solving for the other {} sessions",
+            sessions.len() - unlocked_sessions.len(),
+            unlocked_sessions.len()
+        );
+    }
+
+    // everything which needs the real zids has been loaded by now, so only
+    // the outputs see the pseudonyms
+    // the key is written into each output directory so the anonymized solution can
+    // be de-anonymized when it's loaded again (e.g. with --incremental or --stats-only)
+    let mut instructors = instructors;
+    let anonymization_key = if args.anonymize {
+        println!(
+            "Anonymizing instructors in outputs, the key is in {ANONYMIZATION_KEY_FILENAME} of each output directory"
+        );
+        Some(Instructor::anonymize(&mut instructors)?)
+    } else {
+        None
+    };
 
     let problem = Problem {
         sessions: &sessions,
         instructors: &instructors,
         availabilities: &availabilities,
         overlap_sharp: &overlaps_sharp,
-        overlap_padded: &overlaps_padded,
-        overlap_same_day: &overlaps_same_day,
+        overlap_levels: &overlap_levels,
+        lab_leads: &lab_leads,
+        contiguous_groups: &contiguous_groups,
+        continuity_groups: &continuity_groups,
+        concurrent_groups: &concurrent_groups,
+        day_variants: &day_variants,
         cost_config: &cost_config,
         initial_solution: &initial_solution,
+        mismatch_weights: &mismatch_weights,
+        unlocked_sessions: &unlocked_sessions,
+        allow_unassigned: !args.never_unassign,
+        focus_instructor,
+        class_preferences: &class_preferences,
+        enrolments: &enrolments,
+        history: &history,
     };
-    check_problem(problem);
+    check_problem(problem, &classes, &defaulted_instructors);
+
+    let initial_cost = initial_solution
+        .evaluate(problem, None)
+        .0
+        .total_cost(&cost_config);
+    if cost_config.lexicographic_tiers.is_some() {
+        // the lower bound is of the summed costs, so isn't comparable with tiered costs
+        println!("Initial cost is {initial_cost:?} (packed from the lexicographic tiers)");
+    } else {
+        println!(
+            "Initial cost is {initial_cost:?}, and the best achievable cost is at least {:?}",
+            problem.cost_lower_bound()
+        );
+    }
 
     if args.initial_costs {
         println!(
@@ -150,29 +541,175 @@ fn main_impl() -> Result<()> {
     }
     println!();
 
-    let mut thread_pool = Pool::new(args.cpus);
+    if let Some(solution_dir) = &args.stats_only {
+        // re-evaluate an old solution under the current config without solving
+        let solution_tsv_path = solution_dir.join("solution.tsv");
+        if !solution_tsv_path.is_file() {
+            bail!("{} doesn't exist", solution_tsv_path.display());
+        }
+        let (solution, _) = get_initial_solution(
+            &solution_tsv_path,
+            &sessions,
+            &instructors,
+            &filtered_out_classes,
+        )
+        .with_context(|| anyhow!("Failed to process {}", solution_tsv_path.display()))?;
+
+        let solution_costs = solution.evaluate(problem, None).0;
+        println!(
+            "Breakdown of {}:\n{}",
+            solution_tsv_path.display(),
+            indent_lines(&solution_costs.to_string(), 4)
+        );
+        println!(
+            "Total cost: {:?}\n",
+            solution_costs.total_cost(&cost_config)
+        );
+        print!("{}", instructor_stats_from_solution(&problem, &solution)?);
+
+        return Ok(());
+    }
+
+    let num_threads = args.cpus.unwrap_or_else(|| {
+        std::thread::available_parallelism().map_or(1, |cpus| cpus.get() as u32)
+    });
+    println!("Using {num_threads} solver threads");
+    let mut thread_pool = Pool::new(num_threads);
 
     let best_result = &Mutex::new(None);
     let initial_solution = &initial_solution;
+    let num_improved = &AtomicU64::new(0);
+    let num_not_improved = &AtomicU64::new(0);
+    let final_costs = &Mutex::new(Vec::new());
+
+    if !(0.0..=1.0).contains(&args.reheat_factor) {
+        bail!("--reheat-factor should be between 0 and 1");
+    }
+    if args.reheat_after == Some(0) {
+        bail!("--reheat-after should be at least 1");
+    }
+    if !(0.0..=1.0).contains(&args.guided_fraction) {
+        bail!("--guided-fraction should be between 0 and 1");
+    }
+    if !args.boltzmann_k.is_finite() || args.boltzmann_k <= 0.0 {
+        bail!("--boltzmann-k should be positive");
+    }
+
+    let solver_options = SolverOptions {
+        polish: args.polish,
+        reheat_after: args.reheat_after,
+        reheat_factor: args.reheat_factor,
+        restarts_per_seed: args.restarts_per_seed,
+        guided_fraction: args.guided_fraction,
+        stable_rng: args.stable_rng,
+        debug_mutations: args.debug_mutations,
+        boltzmann_k: args.boltzmann_k,
+        accepted_moves: args.accepted_moves,
+    };
+
+    let seed_list = match &args.seed_list {
+        Some(_) if args.start_seed.is_some() => {
+            bail!("--seed-list and --start-seed can't be used together")
+        }
+        Some(seed_list_path) => Some(SolverSeed::list_from_file(seed_list_path, args.num_rounds)?),
+        None => None,
+    };
+
+    if args.accepted_moves == Some(0) {
+        bail!("--accepted-moves should be at least 1");
+    }
+    if args.max_output_dirs == Some(0) {
+        bail!("--max-output-dirs should be at least 1 so the new output is kept");
+    }
+    let output_options = OutputOptions {
+        full_problem_dump: args.full_problem_dump,
+        max_output_dirs: args.max_output_dirs,
+        email_template: match &args.email_template {
+            Some(path) => fs::read_to_string(path)
+                .with_context(|| anyhow!("failed to read email template {}", path.display()))?,
+            None => DEFAULT_EMAIL_TEMPLATE.into(),
+        },
+        anonymization_key,
+    };
+
+    if args.sample_schedule {
+        let sample = sample_schedule(
+            problem,
+            initial_solution,
+            SolverSeed {
+                num_rounds: 0,
+                rng_seed: args.start_seed.unwrap_or(1),
+            },
+            args.stable_rng,
+        );
+        print!("{}", sample.log);
+        output_solution(problem, &sample, &run_info, &output_options)?;
+        return Ok(());
+    }
+
+    // with a seed list the shortest run is the one most likely to be too short
+    let shortest_num_rounds = match &seed_list {
+        Some(seed_list) => seed_list.iter().map(|seed| seed.num_rounds).min(),
+        None => Some(args.num_rounds),
+    };
+    if let Some(shortest_num_rounds) = shortest_num_rounds {
+        warn_about_short_schedule(problem, shortest_num_rounds, solver_options);
+    }
 
     let run_with_seed = |seed| {
-        let new_result = solve_once(problem, initial_solution, seed);
+        let new_result = solve_once(problem, initial_solution, seed, solver_options);
+        // the short warm up run isn't comparable with the others, but everything in
+        // a seed list is compared with the others run for as many rounds
+        if seed_list.is_some() || seed.num_rounds == args.num_rounds {
+            final_costs
+                .lock()
+                .unwrap()
+                .push((seed.num_rounds, new_result.final_cost));
+        }
         let mut best_result = best_result.lock().unwrap();
 
-        if new_result.better_than(best_result.as_ref()) {
-            output_solution(problem, &new_result).unwrap();
+        if new_result.better_than(
+            best_result.as_ref(),
+            args.deterministic_ties.then_some(initial_solution),
+        ) {
+            num_improved.fetch_add(1, Ordering::Relaxed);
+            // an external baseline (like last week's cost) which is worth beating
+            let beats_baseline = match (args.min_improvement_over, new_result.final_cost) {
+                (None, _) => true,
+                (Some(baseline), Some(new_cost)) => new_cost < baseline,
+                (Some(_), None) => false,
+            };
+            if beats_baseline {
+                output_solution(problem, &new_result, &run_info, &output_options).unwrap();
+            } else if !args.quiet_solver {
+                println!(
+                    "Not writing output from {seed:?} since its cost {:?} doesn't beat {:?}",
+                    new_result.final_cost, args.min_improvement_over
+                );
+            }
             *best_result = Some(new_result);
         } else {
-            println!(
-                "Did not get improvement from {seed:?} (cost {:?})",
-                new_result.final_cost
-            )
+            num_not_improved.fetch_add(1, Ordering::Relaxed);
+            if !args.quiet_solver {
+                println!(
+                    "Did not get improvement from {seed:?} (cost {:?})",
+                    new_result.final_cost
+                )
+            }
         }
     };
 
     thread_pool.scoped(|pool_scope| {
         println!("Starting solving...");
 
+        if let Some(seed_list) = &seed_list {
+            println!("Running the {} seeds from the seed list", seed_list.len());
+            for &seed in seed_list {
+                pool_scope.execute(move || run_with_seed(seed));
+            }
+            return;
+        }
+
         if args.start_seed.is_none() {
             pool_scope.execute(move || {
                 run_with_seed(SolverSeed {
@@ -192,6 +729,39 @@ fn main_impl() -> Result<()> {
         }
     });
 
+    println!(
+        "\nFinished solving: {} attempts gave a new best solution, {} did not",
+        num_improved.load(Ordering::Relaxed),
+        num_not_improved.load(Ordering::Relaxed)
+    );
+
+    if args.compare_seeds {
+        let mut final_costs_by_rounds = BTreeMap::<u64, Vec<_>>::new();
+        for &(num_rounds, final_cost) in final_costs.lock().unwrap().iter() {
+            final_costs_by_rounds
+                .entry(num_rounds)
+                .or_default()
+                .push(final_cost);
+        }
+        for (num_rounds, final_costs) in final_costs_by_rounds {
+            print!(
+                "\nFinal costs across seeds run for {num_rounds} rounds:\n{}",
+                indent_lines(&summarise_final_costs(&final_costs), 4)
+            );
+        }
+    }
+
+    if let Some(best_result) = best_result.lock().unwrap().as_ref() {
+        print!(
+            "\nPreference satisfaction of the best solution:\n{}",
+            indent_lines(&preference_summary(&problem, &best_result.solution)?, 4)
+        );
+
+        if args.robust {
+            print!("\n{}", robustness_report(problem, &best_result.solution));
+        }
+    }
+
     Ok(())
 }
 
@@ -1,10 +1,67 @@
 use crate::{
     evaluator::{Problem, Solution},
     instructor::InstructorId,
-    session::SessionId,
+    rng::SolverRng,
+    session::{OverlapLevel, SessionId},
     talloc::Availability,
+    utils::TwoCombIter,
 };
 
+// The unlocked sessions which are currently contributing cost: unassigned, assigned to
+// someone who dislikes or can't do them, or directly overlapping another of the
+// instructor's sessions. Mutations can be focused on these to speed up convergence.
+#[derive(Default)]
+pub struct HotSessions {
+    sessions: Vec<SessionId>,
+}
+
+impl HotSessions {
+    pub fn recompute(&mut self, problem: Problem, solution: &Solution) {
+        let mut is_hot = vec![false; problem.sessions.len()];
+        let mut instructor_allocations = vec![vec![]; problem.instructors.len()];
+
+        for (session, assignment) in problem.sessions.iter().zip(solution.assignment.iter()) {
+            let session_index = session.session_id.raw_index();
+            match *assignment {
+                Some(instructor_id) => {
+                    is_hot[session_index] = matches!(
+                        problem
+                            .availabilities
+                            .get_availability(session.session_id, instructor_id),
+                        Availability::Impossible | Availability::Dislike
+                    );
+                    instructor_allocations[instructor_id.raw_index()].push(session.session_id);
+                }
+                None => is_hot[session_index] = true,
+            }
+        }
+
+        for instructor_allocation in &instructor_allocations {
+            for (session_1, session_2) in TwoCombIter::new(instructor_allocation) {
+                if session_1 != session_2
+                    && problem.overlap_levels.get(session_1, session_2) == OverlapLevel::Sharp
+                {
+                    is_hot[session_1.raw_index()] = true;
+                    is_hot[session_2.raw_index()] = true;
+                }
+            }
+        }
+
+        self.sessions.clear();
+        self.sessions.extend(
+            problem
+                .unlocked_sessions
+                .iter()
+                .copied()
+                .filter(|session_id| is_hot[session_id.raw_index()]),
+        );
+    }
+
+    pub fn num_sessions(&self) -> usize {
+        self.sessions.len()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Mutation {
     Mult(Box<Mutation>, Box<Mutation>),
@@ -15,29 +72,94 @@ pub enum Mutation {
 }
 
 impl Mutation {
+    // The mutation which changes the session's assignment from `old` to `new`,
+    // or None if there's nothing to change
+    pub fn reassign(
+        session_id: SessionId,
+        old: Option<InstructorId>,
+        new: Option<InstructorId>,
+    ) -> Option<Self> {
+        match (old, new) {
+            (Some(old), Some(new)) if old != new => Some(Mutation::Swap(session_id, old, new)),
+            (Some(old), None) => Some(Mutation::Remove(session_id, old)),
+            (None, Some(new)) => Some(Mutation::Add(session_id, new)),
+            _ => None,
+        }
+    }
+
+    // With probability `guided_fraction` mutate one of the hot sessions, otherwise
+    // fall back to a uniformly random mutation so the solver keeps exploring
+    pub fn make_guided(
+        problem: Problem,
+        solution: &Solution,
+        hot_sessions: &HotSessions,
+        num_classes: &[u16],
+        guided_fraction: f64,
+        rng: &mut SolverRng,
+    ) -> Option<Self> {
+        if !hot_sessions.sessions.is_empty() && rng.f64() < guided_fraction {
+            // the hot sessions are in priority order, so taking the earlier of two
+            // random picks favours the ones which are hardest to staff
+            let num_hot = hot_sessions.sessions.len();
+            let session_id =
+                hot_sessions.sessions[rng.usize(0..num_hot).min(rng.usize(0..num_hot))];
+            Mutation::make_for_session(problem, solution, session_id, num_classes, rng)
+        } else {
+            Mutation::make_random(problem, solution, num_classes, rng)
+        }
+    }
+
+    // `num_classes` is how many sessions each instructor currently has, which is used
+    // to avoid proposing to give more to someone who is already at their maximum
     pub fn make_random(
         problem: Problem,
         solution: &Solution,
-        rng: &mut fastrand::Rng,
+        num_classes: &[u16],
+        rng: &mut SolverRng,
     ) -> Option<Self> {
         if rng.u8(0..8) == 3 {
             return Some(Mutation::Mult(
-                Box::new(Mutation::make_random(problem, solution, rng)?),
-                Box::new(Mutation::make_random(problem, solution, rng)?),
+                Box::new(Mutation::make_random(problem, solution, num_classes, rng)?),
+                Box::new(Mutation::make_random(problem, solution, num_classes, rng)?),
             ));
         }
 
-        let session_index = rng.usize(0..problem.sessions.len());
-        let session_id = SessionId::from_index(session_index);
+        // locked sessions are never changed, so only pick from the rest
+        if problem.unlocked_sessions.is_empty() {
+            return None;
+        }
+        let session_id = problem.unlocked_sessions[rng.usize(0..problem.unlocked_sessions.len())];
+
+        Mutation::make_for_session(problem, solution, session_id, num_classes, rng)
+    }
+
+    // A random change to the assignment of `session_id`, which must be unlocked
+    pub fn make_for_session(
+        problem: Problem,
+        solution: &Solution,
+        session_id: SessionId,
+        num_classes: &[u16],
+        rng: &mut SolverRng,
+    ) -> Option<Self> {
+        let random_unlocked_session = |rng: &mut SolverRng| {
+            problem.unlocked_sessions[rng.usize(0..problem.unlocked_sessions.len())]
+        };
+        let session_index = session_id.raw_index();
 
-        let rand_instructor_for_session = |rng: &mut fastrand::Rng| {
+        let rand_instructor_for_session = |rng: &mut SolverRng| {
             for _ in 0..16 {
-                let instructor_id =
-                    InstructorId::from_index(rng.usize(0..problem.instructors.len()));
-                if problem
-                    .availabilities
-                    .get_availability(session_id, instructor_id)
-                    != Availability::Impossible
+                let instructor_id = problem.focus_instructor.unwrap_or_else(|| {
+                    InstructorId::from_index(rng.usize(0..problem.instructors.len()))
+                });
+                let is_full = num_classes[instructor_id.raw_index()]
+                    >= problem.instructors[instructor_id.raw_index()]
+                        .class_type_requirement
+                        .max_total_classes as u16;
+                if !is_full
+                    && problem
+                        .availabilities
+                        .get_availability(session_id, instructor_id)
+                        != Availability::Impossible
                 {
                     return Some(instructor_id);
                 }
@@ -49,10 +171,10 @@ impl Mutation {
             Some(old_instructor) => {
                 let decision = rng.u8(0..8);
 
-                if decision == 1 {
+                if decision == 1 && problem.allow_unassigned {
                     Some(Mutation::Remove(session_id, old_instructor))
                 } else if decision == 2 {
-                    let other_session = rng.usize(0..problem.sessions.len());
+                    let other_session = random_unlocked_session(rng).raw_index();
                     if other_session == session_index {
                         return None;
                     }
@@ -80,6 +202,32 @@ impl Mutation {
 }
 
 impl Solution {
+    pub fn num_classes_by_instructor(&self, num_instructors: usize) -> Vec<u16> {
+        let mut num_classes = vec![0; num_instructors];
+        for instructor_id in self.assignment.iter().flatten() {
+            num_classes[instructor_id.raw_index()] += 1;
+        }
+        num_classes
+    }
+
+    // Keeps the per-instructor session counts used by `make_random` up to date
+    // after `mutation` has been applied to this solution
+    pub fn update_class_counts(&self, mutation: &Mutation, num_classes: &mut [u16]) {
+        match mutation {
+            // both halves may have been made for the same session, in which case the
+            // instructors they record aren't accurate, so just count again
+            Mutation::Mult(..) => {
+                num_classes.copy_from_slice(&self.num_classes_by_instructor(num_classes.len()))
+            }
+            Mutation::Remove(_, removed) => num_classes[removed.raw_index()] -= 1,
+            Mutation::Add(_, added) => num_classes[added.raw_index()] += 1,
+            Mutation::Swap(_, old, new) => {
+                num_classes[old.raw_index()] -= 1;
+                num_classes[new.raw_index()] += 1;
+            }
+        }
+    }
+
     pub fn apply_mutation(&mut self, mutation: &Mutation) {
         match mutation {
             Mutation::Mult(a, b) => {
@@ -118,3 +266,90 @@ impl Solution {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::SyntheticProblem;
+
+    // A partly filled in solution for the synthetic problem, so mutations of
+    // every kind are possible
+    fn random_solution(problem: Problem, rng: &mut SolverRng) -> Solution {
+        let mut solution = problem.initial_solution.clone();
+        for _ in 0..problem.sessions.len() {
+            let num_classes = solution.num_classes_by_instructor(problem.instructors.len());
+            if let Some(mutation) = Mutation::make_random(problem, &solution, &num_classes, rng) {
+                solution.apply_mutation(&mutation);
+            }
+        }
+        solution
+    }
+
+    fn check_reverses(problem: Problem, solution: &mut Solution, mutation: &Mutation) {
+        let snapshot = solution.clone();
+        let num_instructors = problem.instructors.len();
+        let mut num_classes = solution.num_classes_by_instructor(num_instructors);
+        let original_num_classes = num_classes.clone();
+
+        solution.apply_mutation(mutation);
+        solution.update_class_counts(mutation, &mut num_classes);
+        assert_eq!(
+            num_classes,
+            solution.num_classes_by_instructor(num_instructors),
+            "class counts are wrong after {mutation:?}"
+        );
+
+        solution.reverse_mutation(mutation);
+        assert_eq!(
+            *solution, snapshot,
+            "reversing {mutation:?} didn't restore the solution"
+        );
+        assert_eq!(
+            solution.num_classes_by_instructor(num_instructors),
+            original_num_classes
+        );
+    }
+
+    #[test]
+    fn random_mutations_reverse() {
+        for seed in 0..5 {
+            let synthetic = SyntheticProblem::generate(seed, 30, 12).unwrap();
+            let problem = synthetic.as_problem();
+            let mut rng = SolverRng::with_seed(seed, true);
+            let mut solution = random_solution(problem, &mut rng);
+
+            for _ in 0..2000 {
+                let num_classes = solution.num_classes_by_instructor(problem.instructors.len());
+                if let Some(mutation) =
+                    Mutation::make_random(problem, &solution, &num_classes, &mut rng)
+                {
+                    check_reverses(problem, &mut solution, &mutation);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mult_on_the_same_session_reverses() {
+        let synthetic = SyntheticProblem::generate(0, 30, 12).unwrap();
+        let problem = synthetic.as_problem();
+        let mut rng = SolverRng::with_seed(0, true);
+        let mut solution = random_solution(problem, &mut rng);
+
+        let session_id = SessionId::from_index(0);
+        let [first, second, third] = [0, 1, 2].map(InstructorId::from_index);
+        solution.assignment[session_id.raw_index()] = Some(first);
+
+        let swap_twice = Mutation::Mult(
+            Box::new(Mutation::Swap(session_id, first, second)),
+            Box::new(Mutation::Swap(session_id, second, third)),
+        );
+        check_reverses(problem, &mut solution, &swap_twice);
+
+        let remove_then_add = Mutation::Mult(
+            Box::new(Mutation::Remove(session_id, first)),
+            Box::new(Mutation::Add(session_id, second)),
+        );
+        check_reverses(problem, &mut solution, &remove_then_add);
+    }
+}
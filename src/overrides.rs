@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, HashMap};
+
 use anyhow::{bail, Context, Result};
 
 use crate::{
@@ -21,6 +23,47 @@ impl Availability {
             ],
         )
     }
+
+    pub fn english_name(self) -> &'static str {
+        match self {
+            Availability::Impossible => "impossible",
+            Availability::Dislike => "dislike",
+            Availability::Possible => "possible",
+            Availability::Preferred => "preferred",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum OverrideMode {
+    Set,
+    RaiseTo,
+    LowerTo,
+}
+
+impl OverrideMode {
+    fn from_name(name: &str) -> Option<Self> {
+        if name.trim().is_empty() {
+            return Some(OverrideMode::Set);
+        }
+
+        match_ignore_case(
+            name.trim(),
+            &[
+                (&["set"], OverrideMode::Set),
+                (&["raise_to"], OverrideMode::RaiseTo),
+                (&["lower_to"], OverrideMode::LowerTo),
+            ],
+        )
+    }
+
+    fn apply(self, current: Availability, requested: Availability) -> Availability {
+        match self {
+            OverrideMode::Set => requested,
+            OverrideMode::RaiseTo => current.max(requested),
+            OverrideMode::LowerTo => current.min(requested),
+        }
+    }
 }
 
 fn matches_spec(needle: &str, haystack: &str) -> bool {
@@ -34,12 +77,61 @@ fn matches_spec(needle: &str, haystack: &str) -> bool {
         .any(|possibility| possibility.eq_ignore_ascii_case(needle))
 }
 
-pub fn apply_overrides(
+// The instructors matched by a comma separated list of zids or instructor names (or `*`)
+fn matching_instructors<'a>(
+    spec: &str,
+    instructors: &'a [Instructor],
+) -> Result<Vec<&'a Instructor>> {
+    if spec.trim() == "*" {
+        return Ok(instructors.iter().collect());
+    }
+
+    let mut matched = Vec::new();
+    for entry in spec.split(',') {
+        let entry = entry.trim();
+        if let Some(instructor) = instructors
+            .iter()
+            .find(|instructor| instructor.zid.eq_ignore_ascii_case(entry))
+        {
+            matched.push(instructor);
+            continue;
+        }
+
+        let by_name = instructors
+            .iter()
+            .filter(|instructor| instructor.name.trim().eq_ignore_ascii_case(entry))
+            .collect::<Vec<_>>();
+        if by_name.len() > 1 {
+            bail!(
+                "the name {entry:?} is ambiguous between {}, use a zid instead",
+                by_name
+                    .iter()
+                    .map(|instructor| instructor.zid.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        matched.extend(by_name);
+    }
+
+    // an instructor could be listed by both zid and name
+    matched.sort_by_key(|instructor| instructor.instructor_id.raw_index());
+    matched.dedup_by_key(|instructor| instructor.instructor_id.raw_index());
+
+    Ok(matched)
+}
+
+fn apply_overrides(
     overrides_tsv: &Tsv,
     availabilities: &mut AvailabilityMatrix,
     instructors: &[Instructor],
     sessions: &[Session],
 ) -> Result<()> {
+    // the last override (and what it asked for) applied to each session/instructor pair,
+    // used to report rows which contradict an earlier row
+    let mut applied_by = HashMap::new();
+    let mut conflicts: BTreeMap<(String, String, OverrideMode), usize> = BTreeMap::new();
+
     for row in overrides_tsv {
         let override_name = row.get("name")?;
         let zid = row.get("zid")?;
@@ -52,13 +144,19 @@ pub fn apply_overrides(
                 format!("bad availability for override {override_name}: `{raw_availability}`")
             })?;
 
+        // the mode column is optional, and defaults to just setting the availability
+        let raw_mode = row.get("mode").unwrap_or("");
+        let mode = OverrideMode::from_name(raw_mode).with_context(|| {
+            format!("bad mode for override {override_name}: `{raw_mode}`, expected set/raise_to/lower_to")
+        })?;
+
         let mut total_applied = 0;
 
-        for instructor in instructors {
-            if !matches_spec(&instructor.zid, zid) {
-                continue;
-            }
+        // the zid column can also have instructor names
+        let matched_instructors = matching_instructors(zid, instructors)
+            .with_context(|| format!("bad zid for override {override_name}"))?;
 
+        for instructor in matched_instructors {
             for session in sessions {
                 if !matches_spec(&session.class_name, class_name) {
                     continue;
@@ -73,10 +171,26 @@ pub fn apply_overrides(
                     continue;
                 }
 
+                if let Some((earlier_name, earlier_request)) = applied_by.insert(
+                    (
+                        session.session_id.raw_index(),
+                        instructor.instructor_id.raw_index(),
+                    ),
+                    (override_name.to_string(), (mode, availability)),
+                ) {
+                    if earlier_request != (mode, availability) {
+                        *conflicts
+                            .entry((earlier_name, override_name.to_string(), mode))
+                            .or_default() += 1;
+                    }
+                }
+
+                let current =
+                    availabilities.get_availability(session.session_id, instructor.instructor_id);
                 availabilities.set_availability(
                     session.session_id,
                     instructor.instructor_id,
-                    availability,
+                    mode.apply(current, availability),
                 );
 
                 total_applied += 1;
@@ -90,5 +204,134 @@ pub fn apply_overrides(
         println!("Override {override_name}: {total_applied} applied")
     }
 
+    for ((earlier_name, later_name, later_mode), num_conflicts) in conflicts {
+        // raise_to/lower_to combine with the earlier value rather than replacing it
+        let outcome = match later_mode {
+            OverrideMode::Set => format!("so {later_name} wins"),
+            OverrideMode::RaiseTo => "so the higher of the two applies".to_string(),
+            OverrideMode::LowerTo => "so the lower of the two applies".to_string(),
+        };
+        println!(
+            "Warning: overrides {earlier_name} and {later_name} set different values for {num_conflicts} session/instructor pairs, {outcome}"
+        );
+    }
+
     Ok(())
 }
+
+// Applies each of the override files in order, so the last override applied to a
+// session/instructor pair wins: a later file overrides an earlier one, and within a
+// file a later row overrides an earlier one. Callers should pass the files in
+// alphabetical order of filename.
+pub fn apply_override_files(
+    overrides_tsvs: &[Tsv],
+    availabilities: &mut AvailabilityMatrix,
+    instructors: &[Instructor],
+    sessions: &[Session],
+) -> Result<()> {
+    for overrides_tsv in overrides_tsvs {
+        println!("Applying overrides from {}", overrides_tsv.path());
+        apply_overrides(overrides_tsv, availabilities, instructors, sessions)
+            .with_context(|| format!("Failed to process overrides in {}", overrides_tsv.path()))?;
+    }
+    if overrides_tsvs.is_empty() {
+        println!("No overrides applied");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        classes::{Class, Mode},
+        session::{classes_to_sessions, SessionId},
+        utils::{Day, TimeOfDay},
+    };
+
+    const HEADER: &str = "name\tzid\tclass\ttype\toverride\tmode\n";
+
+    fn setup() -> Result<(Vec<Instructor>, Vec<Session>, AvailabilityMatrix)> {
+        let instructors = Instructor::vec_from_tsvs(
+            &[Tsv::try_from_str(
+                "instructors.tsv",
+                "name\tzid\tminT\tmaxT\tminA\tmaxA\nA\tz1\t0\t1\t0\t1\n",
+            )?],
+            false,
+        )?;
+        let sessions = classes_to_sessions(&[Class {
+            name: "C1".into(),
+            day: Day::Mon,
+            start: TimeOfDay::from_hour(9).expect("hour should be valid"),
+            mode: Mode::F2F,
+            location: String::new(),
+            lab_location: String::new(),
+            ignore_tut: false,
+            ignore_lab: false,
+            continuity_group: None,
+            alternative_days: Vec::new(),
+        }])?;
+        let availabilities =
+            AvailabilityMatrix::from_fn(&instructors, &sessions, |_, _| Availability::Possible);
+        Ok((instructors, sessions, availabilities))
+    }
+
+    fn tut_availability(
+        availabilities: &AvailabilityMatrix,
+        instructors: &[Instructor],
+    ) -> Availability {
+        availabilities.get_availability(SessionId::from_index(0), instructors[0].instructor_id)
+    }
+
+    #[test]
+    fn later_file_wins() -> Result<()> {
+        let (instructors, sessions, mut availabilities) = setup()?;
+        let overrides_tsvs = [
+            Tsv::try_from_str(
+                "overrides_a.tsv",
+                &format!("{HEADER}first\tz1\tC1\ttut\tpreferred\tset\n"),
+            )?,
+            Tsv::try_from_str(
+                "overrides_b.tsv",
+                &format!("{HEADER}second\tz1\tC1\ttut\timpossible\tset\n"),
+            )?,
+        ];
+
+        apply_override_files(
+            &overrides_tsvs,
+            &mut availabilities,
+            &instructors,
+            &sessions,
+        )?;
+        assert_eq!(
+            tut_availability(&availabilities, &instructors),
+            Availability::Impossible
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn later_row_wins() -> Result<()> {
+        let (instructors, sessions, mut availabilities) = setup()?;
+        let overrides_tsv = Tsv::try_from_str(
+            "overrides.tsv",
+            &format!(
+                "{HEADER}first\tz1\tC1\ttut\timpossible\tset\n\
+                 second\tz1\tC1\ttut\tdislike\tset\n"
+            ),
+        )?;
+
+        apply_override_files(
+            &[overrides_tsv],
+            &mut availabilities,
+            &instructors,
+            &sessions,
+        )?;
+        assert_eq!(
+            tut_availability(&availabilities, &instructors),
+            Availability::Dislike
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use std::ops::Range;
+
+// fastrand doesn't promise the same sequence between versions, so archived
+// seeds might not replay after a dependency bump. The stable generator is a
+// plain splitmix64 which will never change.
+pub enum SolverRng {
+    Fastrand(fastrand::Rng),
+    Stable(u64),
+}
+
+impl SolverRng {
+    pub fn with_seed(seed: u64, stable: bool) -> Self {
+        if stable {
+            SolverRng::Stable(seed)
+        } else {
+            SolverRng::Fastrand(fastrand::Rng::with_seed(seed))
+        }
+    }
+
+    // Recorded in the output metadata so it's clear what a seed will replay with
+    pub fn name(stable: bool) -> &'static str {
+        if stable {
+            "splitmix64"
+        } else {
+            "fastrand 2"
+        }
+    }
+
+    fn next_stable(state: &mut u64) -> u64 {
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn usize(&mut self, range: Range<usize>) -> usize {
+        match self {
+            SolverRng::Fastrand(rng) => rng.usize(range),
+            SolverRng::Stable(state) => {
+                assert!(!range.is_empty(), "empty range {range:?}");
+                let len = (range.end - range.start) as u128;
+                range.start + ((Self::next_stable(state) as u128 * len) >> 64) as usize
+            }
+        }
+    }
+
+    pub fn u8(&mut self, range: Range<u8>) -> u8 {
+        match self {
+            SolverRng::Fastrand(rng) => rng.u8(range),
+            SolverRng::Stable(_) => self.usize(range.start as usize..range.end as usize) as u8,
+        }
+    }
+
+    pub fn f32(&mut self) -> f32 {
+        match self {
+            SolverRng::Fastrand(rng) => rng.f32(),
+            SolverRng::Stable(state) => {
+                (Self::next_stable(state) >> 40) as f32 / (1u64 << 24) as f32
+            }
+        }
+    }
+
+    pub fn f64(&mut self) -> f64 {
+        match self {
+            SolverRng::Fastrand(rng) => rng.f64(),
+            SolverRng::Stable(state) => {
+                (Self::next_stable(state) >> 11) as f64 / (1u64 << 53) as f64
+            }
+        }
+    }
+}
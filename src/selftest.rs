@@ -0,0 +1,105 @@
+use anyhow::{bail, Result};
+
+use crate::{
+    benchmark::SyntheticProblem,
+    mutation::Mutation,
+    rng::SolverRng,
+    solver::{solve_once, SolverOptions, SolverSeed},
+    talloc::Availability,
+    utils::indent_lines,
+};
+
+const SELFTEST_PROBLEM_SEEDS: [u64; 3] = [10, 11, 12];
+const SELFTEST_NUM_CLASSES: usize = 30;
+const SELFTEST_NUM_INSTRUCTORS: usize = 12;
+const SELFTEST_SHORT_ROUNDS: u64 = 50_000;
+const SELFTEST_LONG_ROUNDS: u64 = 200_000;
+const SELFTEST_NUM_MUTATIONS: usize = 10_000;
+
+// Solves some small synthetic problems and checks properties which should hold for
+// any problem: a finite cost is found, nothing impossible is assigned, reversing a
+// mutation undoes it, and solving for longer never ends up worse
+pub fn run_selftest() -> Result<()> {
+    let mut failures = Vec::new();
+
+    for problem_seed in SELFTEST_PROBLEM_SEEDS {
+        let synthetic = SyntheticProblem::generate(
+            problem_seed,
+            SELFTEST_NUM_CLASSES,
+            SELFTEST_NUM_INSTRUCTORS,
+        )?;
+        let problem = synthetic.as_problem();
+        let solve = |num_rounds| {
+            solve_once(
+                problem,
+                problem.initial_solution,
+                SolverSeed {
+                    num_rounds,
+                    rng_seed: problem_seed,
+                },
+                SolverOptions {
+                    stable_rng: true,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let short = solve(SELFTEST_SHORT_ROUNDS);
+        let long = solve(SELFTEST_LONG_ROUNDS);
+        println!(
+            "Problem {problem_seed}: cost {:?} after {SELFTEST_SHORT_ROUNDS} rounds, {:?} after {SELFTEST_LONG_ROUNDS} rounds",
+            short.final_cost, long.final_cost
+        );
+
+        match (short.final_cost, long.final_cost) {
+            (_, None) => failures.push(format!("problem {problem_seed}: no finite cost found")),
+            (Some(short_cost), Some(long_cost)) if long_cost > short_cost => failures.push(format!(
+                "problem {problem_seed}: {SELFTEST_LONG_ROUNDS} rounds got cost {long_cost} but {SELFTEST_SHORT_ROUNDS} rounds got {short_cost}"
+            )),
+            _ => {}
+        }
+
+        for (session, assignment) in problem.sessions.iter().zip(long.solution.assignment.iter()) {
+            if let Some(instructor_id) = *assignment {
+                if problem
+                    .availabilities
+                    .get_availability(session.session_id, instructor_id)
+                    == Availability::Impossible
+                {
+                    failures.push(format!(
+                        "problem {problem_seed}: {} is assigned to {instructor_id:?} who is impossible for it",
+                        session.short_description()
+                    ));
+                }
+            }
+        }
+
+        let mut rng = SolverRng::with_seed(problem_seed, true);
+        let mut solution = long.solution.clone();
+        let num_classes = solution.num_classes_by_instructor(problem.instructors.len());
+        for _ in 0..SELFTEST_NUM_MUTATIONS {
+            let Some(mutation) = Mutation::make_random(problem, &solution, &num_classes, &mut rng)
+            else {
+                continue;
+            };
+            solution.apply_mutation(&mutation);
+            solution.reverse_mutation(&mutation);
+            if solution != long.solution {
+                failures.push(format!(
+                    "problem {problem_seed}: reversing {mutation:?} didn't restore the solution"
+                ));
+                break;
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        bail!(
+            "selftest failed:\n{}",
+            indent_lines(&failures.join("\n"), 4)
+        );
+    }
+
+    println!("Selftest passed");
+    Ok(())
+}
@@ -1,13 +1,18 @@
-use std::fmt::Write as _;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+};
 
+use anyhow::{bail, Context, Result};
 use bit_set::BitSet;
 
 use crate::{
     classes::{Class, Mode, LAB_DURATION_HOURS, TUT_DURATION_HOURS},
+    instructor::InstructorId,
     utils::{Day, SessionDuration, TimeOfDay},
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum SessionType {
     TutLab,
     LabAssist,
@@ -34,49 +39,81 @@ pub struct Session {
     pub duration: SessionDuration,
     pub typ: SessionType,
     pub mode: Mode,
+    pub location: Box<str>,
     pub class_name: Box<str>,
 }
 
-fn class_to_sessions(class: &Class) -> Vec<Session> {
+fn class_to_sessions(class: &Class) -> Result<Vec<Session>> {
     let mut sessions = Vec::new();
 
-    if !class.ignore_tut {
-        sessions.push(Session {
-            session_id: SessionId::default(),
-            day: class.day,
-            start_time: class.start,
-            duration: SessionDuration::new(TUT_DURATION_HOURS + LAB_DURATION_HOURS),
-            typ: SessionType::TutLab,
-            mode: class.mode,
-            class_name: class.name.clone().into(),
-        });
+    for day in class.candidate_days() {
+        let class_name: Box<str> = class.name_on_day(day).into();
+
+        if !class.ignore_tut {
+            sessions.push(Session {
+                session_id: SessionId::default(),
+                day,
+                start_time: class.start,
+                duration: SessionDuration::new(TUT_DURATION_HOURS + LAB_DURATION_HOURS),
+                typ: SessionType::TutLab,
+                mode: class.mode,
+                location: class.location.as_str().into(),
+                class_name: class_name.clone(),
+            });
+        }
+
+        if !class.ignore_lab {
+            sessions.push(Session {
+                session_id: SessionId::default(),
+                day,
+                start_time: class
+                    .start
+                    .add_hr(TUT_DURATION_HOURS)
+                    .with_context(|| format!("class {} extends past midnight", class.name))?,
+                duration: SessionDuration::new(LAB_DURATION_HOURS),
+                typ: SessionType::LabAssist,
+                mode: class.mode,
+                location: class.lab_location.as_str().into(),
+                class_name,
+            });
+        }
     }
 
-    if !class.ignore_lab {
-        sessions.push(Session {
-            session_id: SessionId::default(),
-            day: class.day,
-            start_time: class.start.add_hr(TUT_DURATION_HOURS),
-            duration: SessionDuration::new(LAB_DURATION_HOURS),
-            typ: SessionType::LabAssist,
-            mode: class.mode,
-            class_name: class.name.clone().into(),
-        });
+    for session in &sessions {
+        if session.start_time.add_duration(session.duration).is_none() {
+            bail!(
+                "{} starting at {} extends past midnight",
+                session.short_description(),
+                session.start_time
+            );
+        }
     }
 
-    sessions
+    Ok(sessions)
 }
 
-pub fn classes_to_sessions(classes: &[Class]) -> Vec<Session> {
-    classes
+pub fn classes_to_sessions(classes: &[Class]) -> Result<Vec<Session>> {
+    Ok(classes
         .iter()
-        .flat_map(class_to_sessions)
+        .map(class_to_sessions)
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
         .enumerate()
         .map(|(idx, mut session)| {
             session.session_id = SessionId(idx as _);
             session
         })
-        .collect()
+        .collect())
+}
+
+// The building part of a location like "Quadrangle G040", which is everything
+// before the room number (or the whole location if there's no room number)
+fn building(location: &str) -> &str {
+    match location.trim().rsplit_once(' ') {
+        Some((building, room)) if room.chars().any(|c| c.is_ascii_digit()) => building.trim(),
+        _ => location.trim(),
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -87,6 +124,14 @@ pub enum OverlapRequirement {
 }
 
 impl Session {
+    // Whether going between this and `other` means moving between different
+    // buildings, which is never the case for online sessions
+    pub fn changes_location_to(&self, other: &Session) -> bool {
+        self.mode == Mode::F2F
+            && other.mode == Mode::F2F
+            && !building(&self.location).eq_ignore_ascii_case(building(&other.location))
+    }
+
     fn overlaps_with(&self, other: &Session, mut requirement: OverlapRequirement) -> bool {
         if self.day != other.day {
             return false;
@@ -102,28 +147,42 @@ impl Session {
         }
 
         // self ends before other
-        if self.start_time.add_duration(self.duration) < other.start_time {
+        if self.end_time() < other.start_time {
             return false;
         }
-        if matches!(requirement, OverlapRequirement::Sharp)
-            && self.start_time.add_duration(self.duration) <= other.start_time
-        {
+        if matches!(requirement, OverlapRequirement::Sharp) && self.end_time() <= other.start_time {
             return false;
         }
 
         // other ends before self
-        if other.start_time.add_duration(self.duration) < self.start_time {
+        if other.end_time() < self.start_time {
             return false;
         }
-        if matches!(requirement, OverlapRequirement::Sharp)
-            && other.start_time.add_duration(self.duration) <= self.start_time
-        {
+        if matches!(requirement, OverlapRequirement::Sharp) && other.end_time() <= self.start_time {
             return false;
         }
 
         true
     }
 
+    // How many whole hours the two sessions share, ignoring any padding
+    fn overlap_hours(&self, other: &Session) -> u8 {
+        if self.day != other.day {
+            return 0;
+        }
+
+        let start = self.start_time.max(other.start_time);
+        let end = self.end_time().min(other.end_time());
+
+        end.as_24_hours().saturating_sub(start.as_24_hours())
+    }
+
+    pub fn end_time(&self) -> TimeOfDay {
+        self.start_time
+            .add_duration(self.duration)
+            .expect("sessions past midnight are rejected by classes_to_sessions")
+    }
+
     pub fn short_description(&self) -> String {
         format!(
             "{} {}",
@@ -139,6 +198,7 @@ impl Session {
 pub struct OverlapMatrix {
     num_sessions: usize,
     overlaps: BitSet,
+    overlap_hours: Vec<u8>,
 }
 
 // A precomputed store of which sessions overlap with each other
@@ -150,6 +210,7 @@ impl OverlapMatrix {
     pub fn from_sessions(sessions: &[Session], requirement: OverlapRequirement) -> OverlapMatrix {
         let num_sessions = sessions.len();
         let mut overlaps = BitSet::with_capacity(num_sessions * num_sessions);
+        let mut overlap_hours = vec![0; num_sessions * num_sessions];
 
         for session_1 in sessions {
             for session_2 in sessions {
@@ -158,11 +219,13 @@ impl OverlapMatrix {
                 }
 
                 if session_1.overlaps_with(session_2, requirement) {
-                    overlaps.insert(Self::get_overlap_index(
+                    let overlap_index = Self::get_overlap_index(
                         num_sessions,
                         session_1.session_id,
                         session_2.session_id,
-                    ));
+                    );
+                    overlaps.insert(overlap_index);
+                    overlap_hours[overlap_index] = session_1.overlap_hours(session_2);
                 }
             }
         }
@@ -170,6 +233,7 @@ impl OverlapMatrix {
         OverlapMatrix {
             num_sessions,
             overlaps,
+            overlap_hours,
         }
     }
 
@@ -200,4 +264,442 @@ impl OverlapMatrix {
             session_2,
         ))
     }
+
+    // The number of hours two overlapping sessions actually share, which may
+    // be zero if they only overlap because of padding
+    pub fn overlap_hours(&self, session_1: SessionId, session_2: SessionId) -> u8 {
+        self.overlap_hours[Self::get_overlap_index(self.num_sessions, session_1, session_2)]
+    }
+}
+
+// The most severe kind of overlap between two sessions, in the same order
+// that the evaluator checks them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OverlapLevel {
+    None,
+    SameDay,
+    Padded,
+    Sharp,
+}
+
+// A combination of the sharp/padded/same day overlap matrices so that a
+// single lookup in the evaluator's inner loop gives the overlap category
+pub struct OverlapLevels {
+    num_sessions: usize,
+    levels: Vec<OverlapLevel>,
+}
+
+impl OverlapLevels {
+    pub fn from_matrices(
+        sessions: &[Session],
+        sharp: &OverlapMatrix,
+        padded: &OverlapMatrix,
+        same_day: &OverlapMatrix,
+    ) -> OverlapLevels {
+        let num_sessions = sessions.len();
+        let mut levels = Vec::with_capacity(num_sessions * num_sessions);
+
+        for session_1 in sessions {
+            for session_2 in sessions {
+                let (session_1, session_2) = (session_1.session_id, session_2.session_id);
+                levels.push(if sharp.is_overlap(session_1, session_2) {
+                    OverlapLevel::Sharp
+                } else if padded.is_overlap(session_1, session_2) {
+                    OverlapLevel::Padded
+                } else if same_day.is_overlap(session_1, session_2) {
+                    OverlapLevel::SameDay
+                } else {
+                    OverlapLevel::None
+                });
+            }
+        }
+
+        OverlapLevels {
+            num_sessions,
+            levels,
+        }
+    }
+
+    pub fn get(&self, session_1: SessionId, session_2: SessionId) -> OverlapLevel {
+        self.levels[OverlapMatrix::get_overlap_index(self.num_sessions, session_1, session_2)]
+    }
+
+    // A Graphviz graph with a node per session and an edge for each overlap,
+    // labelled with how severe it is
+    pub fn to_dot(&self, sessions: &[Session]) -> String {
+        let mut dot = String::from("graph overlaps {\n");
+
+        for session in sessions {
+            writeln!(
+                &mut dot,
+                "    s{} [label=\"{}\\n{} {}\"];",
+                session.session_id.raw_index(),
+                session.short_description().replace('"', "\\\""),
+                session.day,
+                session.start_time
+            )
+            .unwrap();
+        }
+
+        for session_1 in sessions {
+            for session_2 in sessions {
+                if session_1.session_id.raw_index() >= session_2.session_id.raw_index() {
+                    continue;
+                }
+                let (label, style) = match self.get(session_1.session_id, session_2.session_id) {
+                    OverlapLevel::None => continue,
+                    OverlapLevel::SameDay => ("same day", "dotted"),
+                    OverlapLevel::Padded => ("padded", "dashed"),
+                    OverlapLevel::Sharp => ("sharp", "bold"),
+                };
+                writeln!(
+                    &mut dot,
+                    "    s{} -- s{} [label=\"{label}\", style={style}];",
+                    session_1.session_id.raw_index(),
+                    session_2.session_id.raw_index()
+                )
+                .unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// For each lab assist session, the tut+lab sessions running at the same time
+// whose instructor could act as the lead
+#[derive(Default)]
+pub struct LabLeads {
+    pub leads: Vec<(SessionId, Vec<SessionId>)>,
+}
+
+impl LabLeads {
+    pub fn from_sessions(sessions: &[Session]) -> LabLeads {
+        let leads = sessions
+            .iter()
+            .filter(|session| session.typ == SessionType::LabAssist)
+            .map(|lab_session| {
+                let lead_sessions = sessions
+                    .iter()
+                    .filter(|session| {
+                        session.typ == SessionType::TutLab && session.overlap_hours(lab_session) > 0
+                    })
+                    .map(|session| session.session_id)
+                    .collect();
+                (lab_session.session_id, lead_sessions)
+            })
+            .collect();
+
+        LabLeads { leads }
+    }
+}
+
+// The sessions of classes which share a `continuity_group` in classes.tsv, which
+// should all be taught by the same instructor. Each group is split by session type,
+// since a class's lab assist can't be taught by its tut+lab instructor.
+#[derive(Debug, Default)]
+pub struct ContinuityGroups {
+    pub groups: Vec<Vec<SessionId>>,
+}
+
+impl ContinuityGroups {
+    pub fn from_classes(classes: &[Class], sessions: &[Session]) -> ContinuityGroups {
+        let mut groups: BTreeMap<(&str, SessionType), Vec<SessionId>> = BTreeMap::new();
+        for class in classes {
+            let Some(group) = &class.continuity_group else {
+                continue;
+            };
+            // the sessions for every day the class could run on, since only the
+            // ones on the chosen day will be assigned
+            let class_names = class
+                .candidate_days()
+                .map(|day| class.name_on_day(day))
+                .collect::<Vec<_>>();
+            for session in sessions
+                .iter()
+                .filter(|session| class_names.iter().any(|name| *session.class_name == **name))
+            {
+                groups
+                    .entry((group, session.typ))
+                    .or_default()
+                    .push(session.session_id);
+            }
+        }
+
+        ContinuityGroups {
+            groups: groups
+                .into_values()
+                .filter(|group_sessions| group_sessions.len() > 1)
+                .collect(),
+        }
+    }
+}
+
+// Sets of sessions which run at the same time, being every session along with all
+// the sessions it directly overlaps (with duplicate sets removed)
+#[derive(Debug, Default)]
+pub struct ConcurrentGroups {
+    pub groups: Vec<Vec<SessionId>>,
+}
+
+impl ConcurrentGroups {
+    pub fn from_sessions(sessions: &[Session], overlaps: &OverlapMatrix) -> ConcurrentGroups {
+        let groups = sessions
+            .iter()
+            .map(|session| {
+                sessions
+                    .iter()
+                    .filter(|other| {
+                        other.session_id == session.session_id
+                            || overlaps.is_overlap(session.session_id, other.session_id)
+                    })
+                    .map(|other| other.session_id.raw_index())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|group| group.len() > 1)
+            .collect::<BTreeSet<_>>();
+
+        ConcurrentGroups {
+            groups: groups
+                .into_iter()
+                .map(|group| group.into_iter().map(SessionId::from_index).collect())
+                .collect(),
+        }
+    }
+}
+
+// For classes with alternative days, the sessions for each candidate day. Only one
+// day should be used, which is whichever has sessions assigned, and the sessions
+// for the other days don't count as unassigned.
+#[derive(Debug, Default)]
+pub struct DayVariants {
+    pub groups: Vec<Vec<Vec<SessionId>>>,
+}
+
+impl DayVariants {
+    pub fn from_classes(classes: &[Class], sessions: &[Session]) -> DayVariants {
+        let groups = classes
+            .iter()
+            .filter(|class| !class.alternative_days.is_empty())
+            .map(|class| {
+                class
+                    .candidate_days()
+                    .map(|day| {
+                        let name = class.name_on_day(day);
+                        sessions
+                            .iter()
+                            .filter(|session| *session.class_name == *name)
+                            .map(|session| session.session_id)
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        DayVariants { groups }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    // Marks the sessions of the days which aren't being used by `assignment` as
+    // inactive. A day is used if any of its sessions are assigned, or if nothing is
+    // assigned it's the timetabled day. Returns how many extra days are in use.
+    pub fn mark_inactive(
+        &self,
+        assignment: &[Option<InstructorId>],
+        inactive: &mut [bool],
+    ) -> usize {
+        let mut num_extra_days = 0;
+        for variants in &self.groups {
+            let is_used = |variant: &Vec<SessionId>| {
+                variant
+                    .iter()
+                    .any(|session_id| assignment[session_id.raw_index()].is_some())
+            };
+            let num_used = variants.iter().filter(|variant| is_used(variant)).count();
+            num_extra_days += num_used.saturating_sub(1);
+
+            for (idx, variant) in variants.iter().enumerate() {
+                let is_active = if num_used == 0 {
+                    idx == 0
+                } else {
+                    is_used(variant)
+                };
+                for session_id in variant {
+                    inactive[session_id.raw_index()] = !is_active;
+                }
+            }
+        }
+        num_extra_days
+    }
+
+    // The sessions on the unused days, which don't count as unassigned
+    pub fn inactive_sessions(&self, assignment: &[Option<InstructorId>]) -> Vec<bool> {
+        let mut inactive = vec![false; assignment.len()];
+        self.mark_inactive(assignment, &mut inactive);
+        inactive
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{classes::Class, utils::Day};
+
+    fn class(name: &str, day: Day, hour: u8, mode: Mode) -> Class {
+        Class {
+            name: name.into(),
+            day,
+            start: TimeOfDay::from_hour(hour).expect("hour should be valid"),
+            mode,
+            location: String::new(),
+            lab_location: String::new(),
+            ignore_tut: false,
+            ignore_lab: false,
+            continuity_group: None,
+            alternative_days: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn overlap_levels_match_the_matrices() -> Result<()> {
+        // each class is a tut+lab then a lab assist session, in this order
+        let sessions = classes_to_sessions(&[
+            class("A", Day::Mon, 9, Mode::F2F),
+            class("B", Day::Mon, 12, Mode::F2F),
+            class("C", Day::Mon, 16, Mode::F2F),
+            class("D", Day::Tue, 9, Mode::F2F),
+            class("E", Day::Mon, 12, Mode::Online),
+        ])?;
+        let a_tut = SessionId::from_index(0);
+        let a_lab = SessionId::from_index(1);
+        let b_tut = SessionId::from_index(2);
+        let b_lab = SessionId::from_index(3);
+        let c_tut = SessionId::from_index(4);
+        let d_tut = SessionId::from_index(6);
+        let e_tut = SessionId::from_index(8);
+
+        let sharp = OverlapMatrix::from_sessions(&sessions, OverlapRequirement::Sharp);
+        let padded = OverlapMatrix::from_sessions(&sessions, OverlapRequirement::WithPadding);
+        let same_day = OverlapMatrix::from_sessions(&sessions, OverlapRequirement::SameDay);
+        let levels = OverlapLevels::from_matrices(&sessions, &sharp, &padded, &same_day);
+
+        for (session_1, session_2, expected) in [
+            // a class's tut+lab and lab assist run at the same time
+            (a_tut, a_lab, OverlapLevel::Sharp),
+            // back to back
+            (a_tut, b_tut, OverlapLevel::Padded),
+            (a_lab, b_tut, OverlapLevel::Padded),
+            // with a break in between
+            (b_tut, c_tut, OverlapLevel::SameDay),
+            (b_lab, c_tut, OverlapLevel::SameDay),
+            (a_tut, c_tut, OverlapLevel::SameDay),
+            (a_tut, d_tut, OverlapLevel::None),
+            // switching between face to face and online needs a break
+            (a_tut, e_tut, OverlapLevel::Sharp),
+        ] {
+            assert_eq!(levels.get(session_1, session_2), expected);
+            assert_eq!(levels.get(session_2, session_1), expected);
+        }
+
+        // every pair agrees with checking the matrices from most to least severe
+        for session_1 in &sessions {
+            for session_2 in &sessions {
+                let (session_1, session_2) = (session_1.session_id, session_2.session_id);
+                let expected = if sharp.is_overlap(session_1, session_2) {
+                    OverlapLevel::Sharp
+                } else if padded.is_overlap(session_1, session_2) {
+                    OverlapLevel::Padded
+                } else if same_day.is_overlap(session_1, session_2) {
+                    OverlapLevel::SameDay
+                } else {
+                    OverlapLevel::None
+                };
+                assert_eq!(levels.get(session_1, session_2), expected);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn overlaps_use_each_sessions_own_end_time() -> Result<()> {
+        let mut lab_only = class("B", Day::Mon, 11, Mode::F2F);
+        lab_only.ignore_tut = true;
+        let sessions = classes_to_sessions(&[class("A", Day::Mon, 10, Mode::F2F), lab_only])?;
+        // A's tut+lab runs 10-13 and B's lab assist 12-14
+        let (tut_lab, lab_assist) = (&sessions[0], &sessions[2]);
+
+        // this used to only be a clash from the tut+lab's side, since from the lab
+        // assist's side the tut+lab was taken to end after the lab assist's 2 hours
+        assert!(tut_lab.overlaps_with(lab_assist, OverlapRequirement::Sharp));
+        assert!(lab_assist.overlaps_with(tut_lab, OverlapRequirement::Sharp));
+        Ok(())
+    }
+
+    #[test]
+    fn continuity_groups_are_split_by_session_type() -> Result<()> {
+        let mut classes = [
+            class("A", Day::Mon, 9, Mode::F2F),
+            class("B", Day::Tue, 9, Mode::F2F),
+            class("C", Day::Wed, 9, Mode::F2F),
+        ];
+        classes[0].continuity_group = Some("slot".into());
+        classes[1].continuity_group = Some("slot".into());
+        let sessions = classes_to_sessions(&classes)?;
+
+        // A and B's tut+labs, then their lab assists
+        let groups = ContinuityGroups::from_classes(&classes, &sessions).groups;
+        let expected = [[0, 2], [1, 3]].map(|group| group.map(SessionId::from_index).to_vec());
+        assert_eq!(groups, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn unused_alternative_days_are_inactive() -> Result<()> {
+        let mut class = class("A", Day::Mon, 9, Mode::F2F);
+        class.alternative_days = vec![Day::Wed];
+        let classes = [class];
+        let sessions = classes_to_sessions(&classes)?;
+        let day_variants = DayVariants::from_classes(&classes, &sessions);
+        let session_on = |name: &str| {
+            sessions
+                .iter()
+                .find(|session| &*session.class_name == name)
+                .expect("session should exist")
+                .session_id
+                .raw_index()
+        };
+        let (monday, wednesday) = (session_on("A"), session_on("A@Wed"));
+
+        // with nothing assigned the timetabled day is used
+        let mut assignment = vec![None; sessions.len()];
+        let inactive = day_variants.inactive_sessions(&assignment);
+        assert!(!inactive[monday] && inactive[wednesday]);
+
+        assignment[wednesday] = Some(InstructorId::from_index(0));
+        let inactive = day_variants.inactive_sessions(&assignment);
+        assert!(inactive[monday] && !inactive[wednesday]);
+        Ok(())
+    }
+
+    #[test]
+    fn location_changes_are_between_buildings() -> Result<()> {
+        let mut a = class("A", Day::Mon, 9, Mode::F2F);
+        a.location = "Quadrangle G040".into();
+        a.lab_location = "Ainsworth 201".into();
+        let mut b = class("B", Day::Mon, 12, Mode::F2F);
+        b.location = "Quadrangle 1001".into();
+        b.lab_location = "Quadrangle G040".into();
+        // A's tut+lab, A's lab assist, B's tut+lab, B's lab assist
+        let sessions = classes_to_sessions(&[a, b])?;
+
+        assert!(!sessions[0].changes_location_to(&sessions[2]));
+        assert!(sessions[1].changes_location_to(&sessions[3]));
+        assert!(!sessions[2].changes_location_to(&sessions[3]));
+        Ok(())
+    }
 }
@@ -3,21 +3,33 @@ use std::{
     fs::{self},
     path::{Path, PathBuf},
     sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{anyhow, Context, Result};
 use itertools::Itertools;
+use serde::Serialize;
 
 use crate::{
+    costs::CostValue,
     evaluator::{Problem, Solution},
-    instructor::InstructorId,
-    session::SessionType,
+    instructor::{InstructorId, ANONYMIZATION_KEY_FILENAME},
+    rng::SolverRng,
+    session::{Session, SessionId, SessionType},
     solver::SolverOutput,
-    utils::indent_lines,
+    talloc::Availability,
+    tsv::Tsv,
+    utils::{indent_lines, TwoCombIter},
 };
 
 impl Problem<'_> {
-    pub fn details(&self) -> String {
+    // The full dump of sessions, instructors and availabilities can be megabytes
+    // for large cohorts, so by default only a summary is given
+    pub fn details(&self, full: bool) -> String {
+        if !full {
+            return self.summary();
+        }
+
         let mut result = String::new();
 
         result.push_str("Sessions:\n");
@@ -45,6 +57,39 @@ impl Problem<'_> {
 
         result
     }
+
+    fn summary(&self) -> String {
+        let num_tuts = self
+            .sessions
+            .iter()
+            .filter(|session| matches!(session.typ, SessionType::TutLab))
+            .count();
+        let num_feasible_pairs = self
+            .sessions
+            .iter()
+            .cartesian_product(self.instructors)
+            .filter(|(session, instructor)| {
+                self.availabilities
+                    .get_availability(session.session_id, instructor.instructor_id)
+                    != Availability::Impossible
+            })
+            .count();
+
+        let mut result = format!(
+            "{} sessions ({} tut+labs, {} lab assists), {} unlocked\n{} instructors\n{} feasible session/instructor pairs\n(use --full-problem-dump for all the details)\n",
+            self.sessions.len(),
+            num_tuts,
+            self.sessions.len() - num_tuts,
+            self.unlocked_sessions.len(),
+            self.instructors.len(),
+            num_feasible_pairs,
+        );
+
+        result.push_str("\nCosts:\n");
+        result.push_str(&indent_lines(&format!("{:#?}", self.cost_config), 4));
+
+        result
+    }
 }
 
 pub fn instructor_stats_from_solution(problem: &Problem, solution: &Solution) -> Result<String> {
@@ -112,37 +157,456 @@ pub fn instructor_stats_from_solution(problem: &Problem, solution: &Solution) ->
     Ok(output)
 }
 
-fn solution_output_tsv(problem: &Problem, solution: &Solution) -> String {
-    String::from("class\ttype\tzid\tname\n")
-        + &problem
+// How the assigned sessions are spread across availability levels, overall and
+// per instructor, as a headline measure of quality that's easier to communicate
+// than the total cost
+pub fn preference_summary(problem: &Problem, solution: &Solution) -> Result<String> {
+    let levels = [
+        Availability::Preferred,
+        Availability::Possible,
+        Availability::Dislike,
+        Availability::Impossible,
+    ];
+    let count_levels = |assigned: &mut dyn Iterator<Item = (SessionId, InstructorId)>| {
+        let mut counts = [0usize; 4];
+        for (session_id, instructor_id) in assigned {
+            let availability = problem
+                .availabilities
+                .get_availability(session_id, instructor_id);
+            counts[levels
+                .iter()
+                .position(|&level| level == availability)
+                .unwrap()] += 1;
+        }
+        counts
+    };
+    let describe = |counts: [usize; 4]| {
+        let total = counts.iter().sum::<usize>().max(1) as f64;
+        levels
+            .iter()
+            .zip(counts)
+            .filter(|&(&level, count)| count > 0 || level != Availability::Impossible)
+            .map(|(level, count)| {
+                format!("{:.0}% {level:?} ({count})", 100.0 * count as f64 / total)
+            })
+            .join(", ")
+    };
+
+    let mut output = String::new();
+    let overall = count_levels(&mut problem.sessions.iter().filter_map(|session| {
+        solution.assignment[session.session_id.raw_index()]
+            .map(|instructor_id| (session.session_id, instructor_id))
+    }));
+    writeln!(
+        output,
+        "Assigned sessions: {}\n\nBy instructor:",
+        describe(overall)
+    )?;
+
+    for instructor in problem.instructors {
+        let counts = count_levels(&mut problem.sessions.iter().filter_map(|session| {
+            (solution.assignment[session.session_id.raw_index()] == Some(instructor.instructor_id))
+                .then_some((session.session_id, instructor.instructor_id))
+        }));
+        if counts.iter().sum::<usize>() == 0 {
+            continue;
+        }
+        writeln!(
+            output,
+            "    {} ({}): {}",
+            instructor.name,
+            instructor.zid,
+            describe(counts)
+        )?;
+    }
+
+    // each session has a capacity of one instructor, so at most one preference per
+    // session can ever be honoured. Since the assigned_preferred cost is only counted
+    // for the assigned instructor this doesn't affect the cost, but the slots which
+    // many people prefer explain why some preferences go unmet.
+    writeln!(
+        output,
+        "\nPopular sessions (preferred by more than one instructor):"
+    )?;
+    let mut num_popular = 0;
+    for session in problem
+        .sessions
+        .iter()
+        .sorted_by_key(|session| std::cmp::Reverse(num_preferring(problem, session.session_id)))
+    {
+        let num_preferring = num_preferring(problem, session.session_id);
+        if num_preferring <= 1 {
+            break;
+        }
+        let honoured =
+            solution.assignment[session.session_id.raw_index()].is_some_and(|instructor_id| {
+                problem
+                    .availabilities
+                    .get_availability(session.session_id, instructor_id)
+                    == Availability::Preferred
+            });
+        writeln!(
+            output,
+            "    {} ({} {}): preferred by {num_preferring}, {} unmet",
+            session.short_description(),
+            session.day,
+            session.start_time,
+            num_preferring - usize::from(honoured)
+        )?;
+        num_popular += 1;
+    }
+    if num_popular == 0 {
+        output.push_str("    none!\n");
+    }
+
+    Ok(output)
+}
+
+fn num_preferring(problem: &Problem, session_id: SessionId) -> usize {
+    problem
+        .instructors
+        .iter()
+        .filter(|instructor| {
+            problem
+                .availabilities
+                .get_availability(session_id, instructor.instructor_id)
+                == Availability::Preferred
+        })
+        .count()
+}
+
+// A ready to paste message for each instructor with their sessions sorted by
+// day and time, keyed by zid
+fn instructor_emails(
+    problem: &Problem,
+    solution: &Solution,
+    template: &str,
+) -> Vec<(String, String)> {
+    problem
+        .instructors
+        .iter()
+        .map(|instructor| {
+            let sessions = problem
+                .sessions
+                .iter()
+                .filter(|session| {
+                    solution.assignment[session.session_id.raw_index()]
+                        == Some(instructor.instructor_id)
+                })
+                .sorted_by_key(|session| (session.day, session.start_time))
+                .map(|session| {
+                    format!(
+                        "    {} {} {}\n",
+                        session.day,
+                        session.start_time,
+                        session.short_description()
+                    )
+                })
+                .collect::<String>();
+            let sessions = if sessions.is_empty() {
+                String::from("    no sessions\n")
+            } else {
+                sessions
+            };
+
+            let email = template
+                .replace("{name}", &instructor.name)
+                .replace("{zid}", &instructor.zid)
+                .replace("{sessions}", &sessions);
+            (instructor.zid.clone(), email)
+        })
+        .collect()
+}
+
+fn solution_output_tsv(problem: &Problem, solution: &Solution) -> Result<String> {
+    let inactive_sessions = problem.day_variants.inactive_sessions(&solution.assignment);
+    let rows = problem
+        .sessions
+        .iter()
+        .filter(|session| !inactive_sessions[session.session_id.raw_index()])
+        .map(|session| {
+            let assigned = solution.assignment[session.session_id.raw_index()];
+
+            let instructor =
+                assigned.map(|instructor_id| &problem.instructors[instructor_id.raw_index()]);
+
+            vec![
+                session.class_name.to_string(),
+                match session.typ {
+                    SessionType::TutLab => "tut+lab",
+                    SessionType::LabAssist => "lab",
+                }
+                .to_string(),
+                instructor.map_or("-".into(), |instructor| instructor.zid.clone()),
+                instructor.map_or("-".into(), |instructor| instructor.name.clone()),
+                assigned.map_or(String::new(), |instructor_id| {
+                    problem
+                        .availabilities
+                        .get_availability(session.session_id, instructor_id)
+                        .english_name()
+                        .to_string()
+                }),
+            ]
+        })
+        .collect();
+
+    Ok(Tsv::from_rows(
+        "solution.tsv",
+        &["class", "type", "zid", "name", "availability"],
+        rows,
+    )?
+    .to_string())
+}
+
+// The same data as solution.tsv, but grouped by instructor (sorted by name)
+// with any unassigned sessions at the end
+fn solution_by_instructor_tsv(problem: &Problem, solution: &Solution) -> Result<String> {
+    let inactive_sessions = problem.day_variants.inactive_sessions(&solution.assignment);
+    let mut rows = Vec::new();
+
+    let instructors = problem
+        .instructors
+        .iter()
+        .sorted_by(|a, b| a.name.cmp(&b.name).then_with(|| a.zid.cmp(&b.zid)))
+        .map(Some)
+        .chain([None]);
+
+    for instructor in instructors {
+        let instructor_id = instructor.map(|instructor| instructor.instructor_id);
+
+        for session in problem.sessions {
+            if solution.assignment[session.session_id.raw_index()] != instructor_id
+                || inactive_sessions[session.session_id.raw_index()]
+            {
+                continue;
+            }
+
+            rows.push(vec![
+                instructor.map_or("-".into(), |instructor| instructor.zid.clone()),
+                instructor.map_or("-".into(), |instructor| instructor.name.clone()),
+                session.class_name.to_string(),
+                match session.typ {
+                    SessionType::TutLab => "tut+lab",
+                    SessionType::LabAssist => "lab",
+                }
+                .to_string(),
+            ]);
+        }
+    }
+
+    Ok(Tsv::from_rows(
+        "solution_by_instructor.tsv",
+        &["zid", "name", "class", "type"],
+        rows,
+    )?
+    .to_string())
+}
+
+// Every session in chronological order (then by class), for seeing who is where when
+fn solution_by_time_tsv(problem: &Problem, solution: &Solution) -> Result<String> {
+    let inactive_sessions = problem.day_variants.inactive_sessions(&solution.assignment);
+    let rows = problem
+        .sessions
+        .iter()
+        .filter(|session| !inactive_sessions[session.session_id.raw_index()])
+        .sorted_by(|a, b| {
+            (a.day, a.start_time, &a.class_name).cmp(&(b.day, b.start_time, &b.class_name))
+        })
+        .map(|session| {
+            let instructor = solution.assignment[session.session_id.raw_index()]
+                .map(|instructor_id| &problem.instructors[instructor_id.raw_index()]);
+            vec![
+                session.day.to_string(),
+                session.start_time.to_string(),
+                session.class_name.to_string(),
+                match session.typ {
+                    SessionType::TutLab => "tut+lab",
+                    SessionType::LabAssist => "lab",
+                }
+                .to_string(),
+                instructor.map_or("-".into(), |instructor| instructor.zid.clone()),
+                instructor.map_or("-".into(), |instructor| instructor.name.clone()),
+            ]
+        })
+        .collect();
+
+    Ok(Tsv::from_rows(
+        "solution_by_time.tsv",
+        &["day", "time", "class", "type", "zid", "name"],
+        rows,
+    )?
+    .to_string())
+}
+
+// The sessions which didn't get assigned, for following up manually
+fn unassigned_sessions_tsv(problem: &Problem, solution: &Solution) -> Result<String> {
+    let inactive_sessions = problem.day_variants.inactive_sessions(&solution.assignment);
+    let rows = problem
+        .sessions
+        .iter()
+        .filter(|session| {
+            solution.assignment[session.session_id.raw_index()].is_none()
+                && !inactive_sessions[session.session_id.raw_index()]
+        })
+        .map(|session| {
+            let num_feasible = problem
+                .availabilities
+                .num_feasible_instructors(session.session_id, problem.instructors);
+
+            vec![
+                session.class_name.to_string(),
+                match session.typ {
+                    SessionType::TutLab => "tut+lab",
+                    SessionType::LabAssist => "lab",
+                }
+                .to_string(),
+                session.day.to_string(),
+                session.start_time.to_string(),
+                num_feasible.to_string(),
+            ]
+        })
+        .collect();
+
+    Ok(Tsv::from_rows(
+        "unassigned.tsv",
+        &["class", "type", "day", "time", "feasible instructors"],
+        rows,
+    )?
+    .to_string())
+}
+
+// The order the solver prioritises sessions in (see `unlocked_sessions`), so
+// convenors can see which classes are treated as the most constrained
+fn session_priority_tsv(problem: &Problem) -> Result<String> {
+    let rows = problem
+        .unlocked_sessions
+        .iter()
+        .enumerate()
+        .map(|(priority, session_id)| {
+            let session = &problem.sessions[session_id.raw_index()];
+            vec![
+                (priority + 1).to_string(),
+                session.class_name.to_string(),
+                match session.typ {
+                    SessionType::TutLab => "tut+lab",
+                    SessionType::LabAssist => "lab",
+                }
+                .to_string(),
+                session.day.to_string(),
+                session.start_time.to_string(),
+                problem
+                    .availabilities
+                    .num_feasible_instructors(*session_id, problem.instructors)
+                    .to_string(),
+            ]
+        })
+        .collect();
+
+    Ok(Tsv::from_rows(
+        "session_priority.tsv",
+        &[
+            "priority",
+            "class",
+            "type",
+            "day",
+            "time",
+            "feasible instructors",
+        ],
+        rows,
+    )?
+    .to_string())
+}
+
+// Sessions which some instructors can only do part of, so convenors can consider
+// manually splitting them (e.g. a lab assist covered by two people)
+fn partial_availability_tsv(problem: &Problem) -> Result<String> {
+    let rows = problem
+        .availabilities
+        .partial_availabilities()
+        .map(|(session_id, instructor_id, possible_hours)| {
+            let session = &problem.sessions[session_id.raw_index()];
+            let instructor = &problem.instructors[instructor_id.raw_index()];
+            let hours = possible_hours
+                .iter()
+                .enumerate()
+                .filter(|&(_, &is_possible)| is_possible)
+                .filter_map(|(hour_offset, _)| session.start_time.add_hr(hour_offset as u8))
+                .map(|hour| hour.to_string())
+                .join(", ");
+            vec![
+                session.class_name.to_string(),
+                match session.typ {
+                    SessionType::TutLab => "tut+lab",
+                    SessionType::LabAssist => "lab",
+                }
+                .to_string(),
+                session.day.to_string(),
+                session.start_time.to_string(),
+                instructor.zid.clone(),
+                instructor.name.clone(),
+                hours,
+            ]
+        })
+        .collect();
+
+    Ok(Tsv::from_rows(
+        "partial_availability.tsv",
+        &[
+            "class",
+            "type",
+            "day",
+            "time",
+            "zid",
+            "name",
+            "possible hours",
+        ],
+        rows,
+    )?
+    .to_string())
+}
+
+// Lists every instructor who is still assigned two directly overlapping sessions
+fn overlap_report(problem: &Problem, solution: &Solution) -> Result<String> {
+    let mut output = String::from("Direct overlaps in solution:\n");
+    let mut num_overlaps = 0;
+
+    for instructor in problem.instructors {
+        let allocation = problem
             .sessions
             .iter()
-            .map(|session| {
-                let session_id = session.session_id;
-                let session = &problem.sessions[session_id.raw_index()];
-
-                let assigned = solution.assignment[session_id.raw_index()];
-
-                let instructor =
-                    assigned.map(|instructor_id| &problem.instructors[instructor_id.raw_index()]);
-
-                format!(
-                    "{}\t{}\t{}\t{}",
-                    session.class_name,
-                    match session.typ {
-                        SessionType::TutLab => "tut+lab",
-                        SessionType::LabAssist => "lab",
-                    },
-                    instructor
-                        .map(|instructor| instructor.zid.as_str())
-                        .unwrap_or("-"),
-                    instructor
-                        .map(|instructor| instructor.name.as_str())
-                        .unwrap_or("-"),
-                )
+            .filter(|session| {
+                solution.assignment[session.session_id.raw_index()]
+                    == Some(instructor.instructor_id)
             })
-            .join("\n")
-        + "\n"
+            .map(|session| session.session_id)
+            .collect::<Vec<_>>();
+
+        for (session_1, session_2) in TwoCombIter::new(&allocation) {
+            if problem.overlap_sharp.is_overlap(session_1, session_2) {
+                let session_1 = &problem.sessions[session_1.raw_index()];
+                let session_2 = &problem.sessions[session_2.raw_index()];
+                writeln!(
+                    output,
+                    "    {} ({}): {} ({} {}) and {} ({} {})",
+                    instructor.name,
+                    instructor.zid,
+                    session_1.short_description(),
+                    session_1.day,
+                    session_1.start_time,
+                    session_2.short_description(),
+                    session_2.day,
+                    session_2.start_time,
+                )?;
+                num_overlaps += 1;
+            }
+        }
+    }
+
+    if num_overlaps == 0 {
+        output.push_str("    none!\n");
+    }
+
+    Ok(output)
 }
 
 fn show_diff(problem: &Problem, solution: &Solution) -> String {
@@ -174,9 +638,214 @@ fn show_diff(problem: &Problem, solution: &Solution) -> String {
     output
 }
 
+// The instructors whose sessions changed from the initial solution, with the
+// sessions they lost and gained, so that exactly those people can be re-notified
+fn reconfirm_tsv(problem: &Problem, solution: &Solution) -> Result<String> {
+    let describe = |session: &Session| {
+        format!(
+            "{} ({} {})",
+            session.short_description(),
+            session.day,
+            session.start_time
+        )
+    };
+
+    let rows = problem
+        .instructors
+        .iter()
+        .sorted_by(|a, b| a.name.cmp(&b.name).then_with(|| a.zid.cmp(&b.zid)))
+        .filter_map(|instructor| {
+            let instructor_id = Some(instructor.instructor_id);
+            let mut lost = Vec::new();
+            let mut gained = Vec::new();
+            for session in problem.sessions {
+                let initial_assignment =
+                    problem.initial_solution.assignment[session.session_id.raw_index()];
+                let new_assignment = solution.assignment[session.session_id.raw_index()];
+                if initial_assignment == instructor_id && new_assignment != instructor_id {
+                    lost.push(describe(session));
+                } else if initial_assignment != instructor_id && new_assignment == instructor_id {
+                    gained.push(describe(session));
+                }
+            }
+
+            if lost.is_empty() && gained.is_empty() {
+                return None;
+            }
+            Some(vec![
+                instructor.zid.clone(),
+                instructor.name.clone(),
+                lost.join(", "),
+                gained.join(", "),
+            ])
+        })
+        .collect();
+
+    Ok(Tsv::from_rows("reconfirm.tsv", &["zid", "name", "lost", "gained"], rows)?.to_string())
+}
+
+// Each constraint's count and cost for the initial solution and the new one, so it's
+// clear which constraints were improved and which were traded off for them
+fn cost_delta_tsv(problem: &Problem, solution: &Solution) -> Result<String> {
+    let format_cost = |cost: Option<CostValue>| match cost {
+        Some(cost) => cost.to_string(),
+        None => "inf".into(),
+    };
+    let format_delta = |initial: Option<CostValue>, new: Option<CostValue>| match (initial, new) {
+        (Some(initial), Some(new)) => format!("{:+}", new as i128 - initial as i128),
+        _ => "-".into(),
+    };
+
+    let initial_counts = problem.initial_solution.evaluate(*problem, None).0;
+    let new_counts = solution.evaluate(*problem, None).0;
+
+    let mut rows = initial_counts
+        .counts()
+        .zip(new_counts.counts())
+        .filter(|((_, initial_count), (_, new_count))| *initial_count > 0 || *new_count > 0)
+        .map(|((constraint, initial_count), (_, new_count))| {
+            let initial_cost = problem.cost_config.cost_of(constraint, initial_count);
+            let new_cost = problem.cost_config.cost_of(constraint, new_count);
+            vec![
+                constraint.toml_name(),
+                initial_count.to_string(),
+                new_count.to_string(),
+                format!("{:+}", new_count as i64 - initial_count as i64),
+                format_cost(initial_cost),
+                format_cost(new_cost),
+                format_delta(initial_cost, new_cost),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let initial_total = initial_counts.total_cost(problem.cost_config);
+    let new_total = new_counts.total_cost(problem.cost_config);
+    rows.push(vec![
+        "total".into(),
+        "-".into(),
+        "-".into(),
+        "-".into(),
+        format_cost(initial_total),
+        format_cost(new_total),
+        format_delta(initial_total, new_total),
+    ]);
+
+    Ok(Tsv::from_rows(
+        "cost_delta.tsv",
+        &[
+            "constraint",
+            "initial_count",
+            "new_count",
+            "count_delta",
+            "initial_cost",
+            "new_cost",
+            "cost_delta",
+        ],
+        rows,
+    )?
+    .to_string())
+}
+
 static OUTPUTTER_MUTEX: Mutex<()> = Mutex::new(());
 
-pub fn output_solution(problem: Problem, output: &SolverOutput) -> Result<()> {
+// Details of how the output was produced, written to meta.json so that
+// archived output directories are self-describing
+#[derive(Debug, Serialize)]
+pub struct RunInfo {
+    version: &'static str,
+    rng: &'static str,
+    started_at_unix_seconds: u64,
+    config_dir: PathBuf,
+    cli_args: Vec<String>,
+}
+
+impl RunInfo {
+    pub fn new(config_dir: &Path, stable_rng: bool) -> Self {
+        RunInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            rng: SolverRng::name(stable_rng),
+            started_at_unix_seconds: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+            config_dir: config_dir.to_path_buf(),
+            cli_args: std::env::args().collect(),
+        }
+    }
+
+    fn to_json(&self, output: &SolverOutput) -> Result<String> {
+        #[derive(Serialize)]
+        struct Meta<'a> {
+            #[serde(flatten)]
+            run_info: &'a RunInfo,
+            num_rounds: u64,
+            rng_seed: u64,
+            final_cost: Option<CostValue>,
+        }
+
+        Ok(serde_json::to_string_pretty(&Meta {
+            run_info: self,
+            num_rounds: output.seed.num_rounds,
+            rng_seed: output.seed.rng_seed,
+            final_cost: output.final_cost,
+        })?)
+    }
+}
+
+pub const DEFAULT_EMAIL_TEMPLATE: &str =
+    "Hi {name},\n\nYour allocation for this term is:\n{sessions}\nThanks!\n";
+
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    pub full_problem_dump: bool,
+    // if set, only this many of the most recent run directories are kept
+    pub max_output_dirs: Option<usize>,
+    // used for emails/<zid>.txt, where {name}, {zid} and {sessions} are substituted
+    pub email_template: String,
+    // with --anonymize, the key to write next to each solution
+    pub anonymization_key: Option<String>,
+}
+
+// Whether the directory name looks like one made by `output_solution`, i.e. `<host>-NNNNNN`
+fn is_run_dir_name(name: &str) -> bool {
+    name.rsplit_once('-').is_some_and(|(host, number)| {
+        !host.is_empty() && number.len() == 6 && number.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+// Deletes all but the `max_output_dirs` most recently modified run directories,
+// never touching `latest` or anything which doesn't look like a run directory
+fn prune_output_dirs(output_root: &Path, max_output_dirs: usize) -> Result<()> {
+    let mut run_dirs = Vec::new();
+    for entry in fs::read_dir(output_root)
+        .with_context(|| anyhow!("failed to list {}", output_root.display()))?
+    {
+        let entry = entry?;
+        let name = entry.file_name();
+        if !entry.file_type()?.is_dir() || !is_run_dir_name(&name.to_string_lossy()) {
+            continue;
+        }
+        run_dirs.push((entry.metadata()?.modified()?, entry.path()));
+    }
+
+    run_dirs.sort();
+    let num_to_delete = run_dirs.len().saturating_sub(max_output_dirs);
+    for (_, path) in &run_dirs[..num_to_delete] {
+        fs::remove_dir_all(path)
+            .with_context(|| anyhow!("failed to delete old output {}", path.display()))?;
+    }
+    if num_to_delete > 0 {
+        println!("Deleted {num_to_delete} old output directories");
+    }
+
+    Ok(())
+}
+
+pub fn output_solution(
+    problem: Problem,
+    output: &SolverOutput,
+    run_info: &RunInfo,
+    options: &OutputOptions,
+) -> Result<()> {
     let outputter_guard = OUTPUTTER_MUTEX.lock().unwrap();
 
     let new_output_dir: &Path = &(0..)
@@ -207,16 +876,57 @@ pub fn output_solution(problem: Problem, output: &SolverOutput) -> Result<()> {
             )
         })?;
 
-        fs::write(output_dir.join("problem.txt"), problem.details()).with_context(|| {
+        fs::write(
+            output_dir.join("problem.txt"),
+            problem.details(options.full_problem_dump),
+        )
+        .with_context(|| {
             format!(
                 "failed to write to {}",
-                output_dir.join("solver_log.txt").display()
+                output_dir.join("problem.txt").display()
             )
         })?;
 
+        fs::write(output_dir.join("meta.json"), run_info.to_json(output)?)?;
+
         fs::write(
             output_dir.join("solution.tsv"),
-            solution_output_tsv(&problem, &output.solution),
+            solution_output_tsv(&problem, &output.solution)?,
+        )?;
+
+        fs::write(
+            output_dir.join("solution_by_instructor.tsv"),
+            solution_by_instructor_tsv(&problem, &output.solution)?,
+        )?;
+
+        fs::write(
+            output_dir.join("solution_by_time.tsv"),
+            solution_by_time_tsv(&problem, &output.solution)?,
+        )?;
+
+        fs::write(
+            output_dir.join("unassigned.tsv"),
+            unassigned_sessions_tsv(&problem, &output.solution)?,
+        )?;
+
+        fs::write(
+            output_dir.join("session_priority.tsv"),
+            session_priority_tsv(&problem)?,
+        )?;
+
+        fs::write(
+            output_dir.join("partial_availability.tsv"),
+            partial_availability_tsv(&problem)?,
+        )?;
+
+        fs::write(
+            output_dir.join("overlaps.txt"),
+            overlap_report(&problem, &output.solution)?,
+        )?;
+
+        fs::write(
+            output_dir.join("preferences.txt"),
+            preference_summary(&problem, &output.solution)?,
         )?;
 
         fs::write(
@@ -229,6 +939,28 @@ pub fn output_solution(problem: Problem, output: &SolverOutput) -> Result<()> {
                 output_dir.join("diff.txt"),
                 show_diff(&problem, &output.solution),
             )?;
+            fs::write(
+                output_dir.join("reconfirm.tsv"),
+                reconfirm_tsv(&problem, &output.solution)?,
+            )?;
+            fs::write(
+                output_dir.join("cost_delta.tsv"),
+                cost_delta_tsv(&problem, &output.solution)?,
+            )?;
+        }
+
+        if let Some(anonymization_key) = &options.anonymization_key {
+            fs::write(
+                output_dir.join(ANONYMIZATION_KEY_FILENAME),
+                anonymization_key,
+            )?;
+        }
+
+        let emails_dir = output_dir.join("emails");
+        fs::create_dir_all(&emails_dir)
+            .with_context(|| anyhow!("failed to create directory {}", emails_dir.display()))?;
+        for (zid, email) in instructor_emails(&problem, &output.solution, &options.email_template) {
+            fs::write(emails_dir.join(format!("{zid}.txt")), email)?;
         }
     }
 
@@ -239,6 +971,10 @@ pub fn output_solution(problem: Problem, output: &SolverOutput) -> Result<()> {
         output.seed
     );
 
+    if let Some(max_output_dirs) = options.max_output_dirs {
+        prune_output_dirs(Path::new("output"), max_output_dirs)?;
+    }
+
     drop(outputter_guard);
     Ok(())
 }
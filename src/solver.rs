@@ -1,9 +1,14 @@
 use crate::{
-    evaluator::{Problem, Solution},
-    mutation::Mutation,
+    costs::CostValue,
+    evaluator::{EvalBuffer, Problem, Solution},
+    instructor::InstructorId,
+    mutation::{HotSessions, Mutation},
+    rng::SolverRng,
+    talloc::Availability,
     utils::indent_lines,
 };
-use std::{fmt::Write as _, time::Instant};
+use anyhow::{anyhow, Context, Result};
+use std::{fmt::Write as _, fs, path::Path, time::Instant};
 
 #[derive(Debug, Clone, Copy)]
 pub struct SolverSeed {
@@ -11,76 +16,356 @@ pub struct SolverSeed {
     pub rng_seed: u64,
 }
 
+impl SolverSeed {
+    // Reads a file with a seed on each line, optionally followed by how many rounds
+    // to run it for (otherwise `default_rounds`). Blank lines and `#` comments are
+    // ignored.
+    pub fn list_from_file(path: &Path, default_rounds: u64) -> Result<Vec<SolverSeed>> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| anyhow!("failed to read seed list {}", path.display()))?;
+
+        contents
+            .lines()
+            .enumerate()
+            .filter_map(|(line_idx, line)| {
+                let line = line.split('#').next().unwrap_or("").trim();
+                (!line.is_empty()).then_some((line_idx + 1, line))
+            })
+            .map(|(line_number, line)| {
+                let parse_number = |field: &str| {
+                    field.replace('_', "").parse::<u64>().with_context(|| {
+                        anyhow!(
+                            "bad number {field:?} on line {line_number} of {}",
+                            path.display()
+                        )
+                    })
+                };
+
+                match line.split_whitespace().collect::<Vec<_>>()[..] {
+                    [rng_seed] => Ok(SolverSeed {
+                        num_rounds: default_rounds,
+                        rng_seed: parse_number(rng_seed)?,
+                    }),
+                    [rng_seed, num_rounds] => Ok(SolverSeed {
+                        num_rounds: parse_number(num_rounds)?,
+                        rng_seed: parse_number(rng_seed)?,
+                    }),
+                    _ => Err(anyhow!(
+                        "line {line_number} of {} should be a seed and optionally a number of rounds",
+                        path.display()
+                    )),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SolverOptions {
+    pub polish: bool,
+    // after this many rounds without a new best cost, move back
+    // `reheat_factor` of the way through the cooling schedule
+    pub reheat_after: Option<u64>,
+    pub reheat_factor: f64,
+    pub restarts_per_seed: u64,
+    // the fraction of mutations which target sessions currently contributing cost
+    pub guided_fraction: f64,
+    pub stable_rng: bool,
+    // check that reversing each rejected mutation restores the solution exactly
+    pub debug_mutations: bool,
+    // the Boltzmann constant in the acceptance probability
+    pub boltzmann_k: f32,
+    // also stop once this many mutations have been accepted (split between the
+    // restarts like the rounds are, but rounding up so each restart gets at least
+    // one), so whichever of this and `num_rounds` is
+    // reached first ends the run. The cooling schedule still follows `num_rounds`.
+    pub accepted_moves: Option<u64>,
+}
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        SolverOptions {
+            polish: false,
+            reheat_after: None,
+            reheat_factor: 0.0,
+            restarts_per_seed: 0,
+            guided_fraction: 0.0,
+            stable_rng: false,
+            debug_mutations: false,
+            boltzmann_k: 1.0,
+            accepted_moves: None,
+        }
+    }
+}
+
 pub struct SolverOutput {
     pub seed: SolverSeed,
-    pub final_cost: Option<u64>,
+    pub final_cost: Option<CostValue>,
     pub log: String,
     pub solution: Solution,
 }
 
+fn is_better_cost(new: Option<CostValue>, old: Option<CostValue>) -> bool {
+    match (new, old) {
+        (None, None) => false,
+        (None, Some(_)) => false,
+        (Some(_), None) => true,
+        (Some(new), Some(old)) => new < old,
+    }
+}
+
 impl SolverOutput {
-    pub fn better_than(&self, other: Option<&SolverOutput>) -> bool {
-        match (self.final_cost, other.and_then(|output| output.final_cost)) {
-            (None, None) => false,
-            (None, Some(_)) => false,
-            (Some(_), None) => true,
-            (Some(new), Some(old)) => new < old,
+    // If `tie_break_from` is given, equal cost solutions are compared by how many
+    // sessions differ from it and then by their assignments, so that the chosen
+    // solution doesn't depend on which thread finished first
+    pub fn better_than(
+        &self,
+        other: Option<&SolverOutput>,
+        tie_break_from: Option<&Solution>,
+    ) -> bool {
+        let other_cost = other.and_then(|output| output.final_cost);
+        if let (Some(other), Some(reference)) = (other, tie_break_from) {
+            if self.final_cost.is_some() && self.final_cost == other_cost {
+                return self.solution.tie_break_key(reference)
+                    < other.solution.tie_break_key(reference);
+            }
         }
+
+        is_better_cost(self.final_cost, other_cost)
     }
 }
 
-pub fn solve_once(problem: Problem, initial_solution: &Solution, seed: SolverSeed) -> SolverOutput {
-    let mut rng = fastrand::Rng::with_seed(seed.rng_seed);
-    let mut solution = initial_solution.clone();
+impl Solution {
+    fn tie_break_key<'a>(&'a self, reference: &Solution) -> (usize, &'a [Option<InstructorId>]) {
+        let num_changed = self
+            .assignment
+            .iter()
+            .zip(reference.assignment.iter())
+            .filter(|(new, old)| new != old)
+            .count();
+        (num_changed, &self.assignment)
+    }
+}
 
-    let mut current_cost = solution
-        .evaluate(problem, None)
-        .0
-        .total_cost(problem.cost_config);
-    let mut log = String::new();
+// The Metropolis criterion: a change which doesn't make the cost worse is always
+// accepted, and otherwise it's accepted with probability exp(-cost_diff / (k * temperature)).
+// Changes with the same cost still go through the random draw (which always accepts
+// them) so the RNG stream, and so each seed's result, doesn't change.
+fn accept(cost_diff: f32, temperature: f32, boltzmann_k: f32, rng: &mut SolverRng) -> bool {
+    if cost_diff < 0.0 {
+        return true;
+    }
+    rng.f32() < (-cost_diff / (boltzmann_k * temperature)).exp()
+}
+
+macro_rules! logln {
+    ( $log:expr, $( $args:expr ),* ) => {{
+        writeln!($log, $( $args ),* ).unwrap();
+        // println!($( $args ),* );
+    }};
+}
+
+// Greedily apply the best single session change until nothing improves the cost
+pub fn polish_solution(
+    problem: Problem,
+    solution: &mut Solution,
+    mut current_cost: CostValue,
+) -> CostValue {
+    let mut eval_buffer_helper = None;
+
+    loop {
+        let mut best_move: Option<(Mutation, CostValue)> = None;
+
+        for &session_id in problem.unlocked_sessions {
+            let old_assignment = solution.assignment[session_id.raw_index()];
+
+            let candidates = problem
+                .instructors
+                .iter()
+                .filter(|instructor| {
+                    problem.can_be_given_sessions(instructor.instructor_id)
+                        && problem
+                            .availabilities
+                            .get_availability(session_id, instructor.instructor_id)
+                            != Availability::Impossible
+                })
+                .map(|instructor| Some(instructor.instructor_id))
+                .chain(problem.allow_unassigned.then_some(None));
+
+            for new_assignment in candidates {
+                let Some(mutation) = Mutation::reassign(session_id, old_assignment, new_assignment)
+                else {
+                    continue;
+                };
+
+                solution.apply_mutation(&mutation);
+                let new_evaluation = solution.evaluate(problem, eval_buffer_helper);
+                eval_buffer_helper = Some(new_evaluation.1);
+                solution.reverse_mutation(&mutation);
+
+                if let Some(new_cost) = new_evaluation.0.total_cost(problem.cost_config) {
+                    let best_cost = best_move.as_ref().map_or(current_cost, |(_, cost)| *cost);
+                    if new_cost < best_cost {
+                        best_move = Some((mutation, new_cost));
+                    }
+                }
+            }
+        }
 
-    macro_rules! logln {
-        ( $( $args:expr ),* ) => {{
-            writeln!(&mut log, $( $args ),* ).unwrap();
-            // println!($( $args ),* );
-        }};
+        match best_move {
+            Some((mutation, new_cost)) => {
+                solution.apply_mutation(&mutation);
+                current_cost = new_cost;
+            }
+            None => return current_cost,
+        }
     }
+}
 
-    let start_time = Instant::now();
-    logln!("Beginning solve with seed {seed:?}");
+// The annealing temperature once `schedule_progress` (from 0 to 1) of the cooling
+// schedule is done
+fn temperature(schedule_progress: f32) -> f32 {
+    let progress = 1.0 - schedule_progress;
+    5000.0 * progress.powi(6) + 0.1
+}
+
+// Below this many rounds per unlocked session while the temperature is high, runs
+// barely explore and act like a greedy hill climb
+const MIN_HOT_ROUNDS_PER_SESSION: f64 = 1000.0;
 
-    logln!("Initial cost: {:?}", current_cost);
-    if current_cost.is_none() {
-        logln!("Warning: initial cost is None, you'll probably get a bad result!");
+// Warns if so few of each run's rounds happen while worse solutions are still being
+// accepted that the annealing can't explore, which looks like it never improves
+pub fn warn_about_short_schedule(problem: Problem, num_rounds: u64, options: SolverOptions) {
+    // the fraction of the schedule where the temperature (times the Boltzmann
+    // constant) is at least 1, so a cost increase of 1 is accepted with probability
+    // at least 1/e. The temperature only decreases so this is all at the start.
+    let boltzmann_k = options.boltzmann_k as f64;
+    let min_temperature = 1.0 / boltzmann_k;
+    let hot_fraction = if min_temperature <= 0.1 {
+        1.0
+    } else {
+        (1.0 - ((min_temperature - 0.1) / 5000.0).powf(1.0 / 6.0)).max(0.0)
+    };
+
+    let num_restarts = options.restarts_per_seed.max(1);
+    let hot_rounds = (num_rounds / num_restarts) as f64 * hot_fraction;
+    let wanted_hot_rounds = MIN_HOT_ROUNDS_PER_SESSION * problem.unlocked_sessions.len() as f64;
+    if problem.unlocked_sessions.is_empty() || hot_rounds >= wanted_hot_rounds {
+        return;
     }
-    logln!("Breakdown of initial cost:");
-    logln!(
-        "{}",
-        indent_lines(&solution.evaluate(problem, None).0.to_string(), 4)
-    );
 
-    let mut eval_buffer_helper = None;
+    if hot_fraction == 0.0 {
+        println!(
+            "Warning: with --boltzmann-k {boltzmann_k} the temperature is never high enough to accept worse solutions, so the solver is just a greedy hill climb"
+        );
+    } else {
+        println!(
+            "Warning: only about {hot_rounds:.0} rounds of each run are hot enough to accept worse solutions, which is too few to explore {} sessions and will act like a greedy hill climb. Consider --num-rounds {:.0} or more (or a larger --boltzmann-k)",
+            problem.unlocked_sessions.len(),
+            (wanted_hot_rounds / hot_fraction).ceil() * num_restarts as f64
+        );
+    }
+}
+
+// A single annealing run starting from `solution`, returning the final cost
+#[allow(clippy::too_many_arguments)]
+fn anneal(
+    problem: Problem,
+    solution: &mut Solution,
+    mut current_cost: Option<CostValue>,
+    num_rounds: u64,
+    accepted_moves: Option<u64>,
+    options: SolverOptions,
+    rng: &mut SolverRng,
+    eval_buffer_helper: &mut Option<EvalBuffer>,
+    log: &mut String,
+) -> Option<CostValue> {
+    // the position in the cooling schedule, which reheating moves backwards
+    let mut schedule_round = 0.0f64;
+    let mut schedule_step = 1.0f64;
+    let mut best_cost = current_cost;
+    let mut rounds_since_improvement = 0;
+    let mut num_accepted = 0;
+
+    // recomputing the hot sessions after every mutation would be too slow, and being
+    // slightly out of date just means some guided mutations are wasted
+    let hot_sessions_interval = 1000;
+    let mut hot_sessions = HotSessions::default();
+    let mut num_classes = solution.num_classes_by_instructor(problem.instructors.len());
+
+    for round_num in 0..num_rounds {
+        if accepted_moves.is_some_and(|accepted_moves| num_accepted >= accepted_moves) {
+            logln!(
+                log,
+                "Stopping after {round_num} rounds since {num_accepted} mutations were accepted"
+            );
+            break;
+        }
+
+        if options.guided_fraction > 0.0 && round_num % hot_sessions_interval == 0 {
+            hot_sessions.recompute(problem, solution);
+        }
 
-    for round_num in 0..seed.num_rounds {
         let reporting_interval = 25000;
         if round_num % reporting_interval == 0 {
-            logln!("After {round_num:9} rounds current cost is {current_cost:?}")
+            logln!(
+                log,
+                "After {round_num:9} rounds current cost is {current_cost:?}"
+            );
+            if options.guided_fraction > 0.0 {
+                logln!(log, "    ({} hot sessions)", hot_sessions.num_sessions());
+            }
         }
 
-        let mutation = match Mutation::make_random(problem, &solution, &mut rng) {
+        if options
+            .reheat_after
+            .is_some_and(|reheat_after| rounds_since_improvement >= reheat_after)
+        {
+            schedule_round *= 1.0 - options.reheat_factor;
+            // speed up the rest of the schedule so it still finishes cold
+            schedule_step = (num_rounds as f64 - schedule_round) / (num_rounds - round_num) as f64;
+            rounds_since_improvement = 0;
+            logln!(
+                log,
+                "Reheating after {round_num} rounds with cost {current_cost:?}"
+            );
+        }
+        let schedule_progress = (schedule_round / num_rounds as f64) as f32;
+        schedule_round += schedule_step;
+        rounds_since_improvement += 1;
+
+        let mutation = match Mutation::make_guided(
+            problem,
+            solution,
+            &hot_sessions,
+            &num_classes,
+            options.guided_fraction,
+            rng,
+        ) {
             Some(mutation) => mutation,
             None => continue,
         };
 
+        let snapshot = options.debug_mutations.then(|| solution.clone());
+        let reverse_mutation = |solution: &mut Solution| {
+            solution.reverse_mutation(&mutation);
+            if let Some(snapshot) = &snapshot {
+                assert_eq!(
+                    solution, snapshot,
+                    "reversing {mutation:?} didn't restore the solution on round {round_num}"
+                );
+            }
+        };
+
         solution.apply_mutation(&mutation);
 
-        let new_evaluation = solution.evaluate(problem, eval_buffer_helper);
-        eval_buffer_helper = Some(new_evaluation.1);
+        let new_evaluation = solution.evaluate(problem, eval_buffer_helper.take());
+        *eval_buffer_helper = Some(new_evaluation.1);
 
         let new_cost = match new_evaluation.0.total_cost(problem.cost_config) {
             Some(new_cost) => new_cost,
             None => {
-                solution.reverse_mutation(&mutation);
+                reverse_mutation(solution);
                 continue;
             }
         };
@@ -91,9 +376,12 @@ pub fn solve_once(problem: Problem, initial_solution: &Solution, seed: SolverSee
                     true
                 } else {
                     let cost_diff = (new_cost - current_cost) as f32;
-                    let progress = 1.0 - (round_num as f32) / (seed.num_rounds as f32);
-                    let temperature = 5000.0 * progress.powi(6) + 0.1;
-                    rng.f32() < (-cost_diff / temperature).exp()
+                    accept(
+                        cost_diff,
+                        temperature(schedule_progress),
+                        options.boltzmann_k,
+                        rng,
+                    )
                 }
             }
             None => true,
@@ -101,20 +389,123 @@ pub fn solve_once(problem: Problem, initial_solution: &Solution, seed: SolverSee
 
         if is_better {
             // logln!(
+            //     log,
             //     "improved cost to {new_cost} (diff {diff:?}) on round {round_num}: {mutation:?}"
             // );
             current_cost = Some(new_cost);
+            num_accepted += 1;
+            solution.update_class_counts(&mutation, &mut num_classes);
+
+            if best_cost.is_none_or(|best_cost| new_cost < best_cost) {
+                best_cost = Some(new_cost);
+                rounds_since_improvement = 0;
+            }
         } else {
-            solution.reverse_mutation(&mutation);
+            reverse_mutation(solution);
         }
     }
 
+    current_cost
+}
+
+pub fn solve_once(
+    problem: Problem,
+    initial_solution: &Solution,
+    seed: SolverSeed,
+    options: SolverOptions,
+) -> SolverOutput {
+    let initial_cost = initial_solution
+        .evaluate(problem, None)
+        .0
+        .total_cost(problem.cost_config);
+    let mut log = String::new();
+
+    let start_time = Instant::now();
+    logln!(log, "Beginning solve with seed {seed:?}");
+
+    logln!(log, "Initial cost: {:?}", initial_cost);
+    if initial_cost.is_none() {
+        logln!(
+            log,
+            "Warning: initial cost is None, you'll probably get a bad result!"
+        );
+    }
+    logln!(log, "Breakdown of initial cost:");
     logln!(
+        log,
+        "{}",
+        indent_lines(&initial_solution.evaluate(problem, None).0.to_string(), 4)
+    );
+
+    let mut eval_buffer_helper = None;
+
+    // the rounds are split evenly between the restarts, each of which starts
+    // from the initial solution, and the best result is kept
+    let num_restarts = options.restarts_per_seed.max(1);
+    let mut best_restart: Option<(Solution, Option<CostValue>)> = None;
+
+    for restart in 0..num_restarts {
+        // the first restart uses the seed directly so a single restart is unchanged
+        let mut rng = SolverRng::with_seed(
+            seed.rng_seed
+                .wrapping_add(restart.wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+            options.stable_rng,
+        );
+        let mut solution = initial_solution.clone();
+
+        let restart_cost = anneal(
+            problem,
+            &mut solution,
+            initial_cost,
+            seed.num_rounds / num_restarts,
+            options
+                .accepted_moves
+                .map(|accepted_moves| accepted_moves.div_ceil(num_restarts)),
+            options,
+            &mut rng,
+            &mut eval_buffer_helper,
+            &mut log,
+        );
+        if num_restarts > 1 {
+            logln!(
+                log,
+                "\nRestart {restart} finished with cost {restart_cost:?}\n"
+            );
+        }
+
+        if best_restart.is_none()
+            || best_restart
+                .as_ref()
+                .is_some_and(|(_, best_cost)| is_better_cost(restart_cost, *best_cost))
+        {
+            best_restart = Some((solution, restart_cost));
+        }
+    }
+
+    let (mut solution, mut current_cost) =
+        best_restart.expect("there should always be at least one restart");
+
+    if options.polish {
+        if let Some(cost_before_polish) = current_cost {
+            let cost_after_polish = polish_solution(problem, &mut solution, cost_before_polish);
+            logln!(
+                log,
+                "\nPolishing changed cost from {cost_before_polish} to {cost_after_polish}"
+            );
+            current_cost = Some(cost_after_polish);
+        } else {
+            logln!(log, "\nSkipping polish since there is no valid solution");
+        }
+    }
+
+    logln!(
+        log,
         "\nFinal cost: {:?}:\n{}",
         current_cost,
         indent_lines(&solution.evaluate(problem, None).0.to_string(), 4)
     );
     logln!(
+        log,
         "\nSolving took {:.3} seconds",
         start_time.elapsed().as_secs_f32()
     );
@@ -126,3 +517,240 @@ pub fn solve_once(problem: Problem, initial_solution: &Solution, seed: SolverSee
         solution,
     }
 }
+
+// Fills each unassigned unlocked session with a random instructor who can take it,
+// without any optimisation. This is only useful for demos and for trying out the
+// outputs quickly.
+pub fn sample_schedule(
+    problem: Problem,
+    initial_solution: &Solution,
+    seed: SolverSeed,
+    stable_rng: bool,
+) -> SolverOutput {
+    let mut rng = SolverRng::with_seed(seed.rng_seed, stable_rng);
+    let mut solution = initial_solution.clone();
+    let mut num_classes = solution.num_classes_by_instructor(problem.instructors.len());
+    let mut log = String::new();
+
+    logln!(log, "Sampling a random schedule with seed {seed:?}");
+
+    let mut num_filled = 0;
+    for &session_id in problem.unlocked_sessions {
+        if solution.assignment[session_id.raw_index()].is_some() {
+            continue;
+        }
+        if let Some(mutation) =
+            Mutation::make_for_session(problem, &solution, session_id, &num_classes, &mut rng)
+        {
+            solution.apply_mutation(&mutation);
+            solution.update_class_counts(&mutation, &mut num_classes);
+            num_filled += 1;
+        }
+    }
+
+    let (costs, _) = solution.evaluate(problem, None);
+    let final_cost = costs.total_cost(problem.cost_config);
+    logln!(
+        log,
+        "Filled {num_filled} sessions, giving cost {final_cost:?}:\n{}",
+        indent_lines(&costs.to_string(), 4)
+    );
+
+    SolverOutput {
+        seed,
+        final_cost,
+        log,
+        solution,
+    }
+}
+
+// Statistics over the final costs from many seeds, to show how much the
+// choice of seed matters
+pub fn summarise_final_costs(final_costs: &[Option<CostValue>]) -> String {
+    let mut costs = final_costs.iter().flatten().copied().collect::<Vec<_>>();
+    costs.sort();
+    let num_infeasible = final_costs.len() - costs.len();
+
+    let mut summary = format!(
+        "{} attempts: {} feasible, {} infeasible\n",
+        final_costs.len(),
+        costs.len(),
+        num_infeasible
+    );
+    let (Some(&best), Some(&worst)) = (costs.first(), costs.last()) else {
+        return summary;
+    };
+
+    let median = costs[costs.len() / 2];
+    let mean = costs.iter().map(|&cost| cost as f64).sum::<f64>() / costs.len() as f64;
+    let std_dev = (costs
+        .iter()
+        .map(|&cost| (cost as f64 - mean).powi(2))
+        .sum::<f64>()
+        / costs.len() as f64)
+        .sqrt();
+    let num_near_best = costs
+        .iter()
+        .filter(|&&cost| cost as f64 <= best as f64 * 1.01)
+        .count();
+
+    writeln!(
+        summary,
+        "min {best}, median {median}, max {worst}, mean {mean:.1}, std dev {std_dev:.1}"
+    )
+    .unwrap();
+    writeln!(
+        summary,
+        "{num_near_best} attempts were within 1% of the best{}",
+        if costs.len() > 2 && (mean - best as f64) > 2.0 * std_dev {
+            ", which is an outlier (more attempts may find better)"
+        } else {
+            ""
+        }
+    )
+    .unwrap();
+
+    summary
+}
+
+// For each instructor, simulate them dropping out of `solution` and repair it by
+// polishing, to find which instructors would be hardest to replace
+pub fn robustness_report(problem: Problem, solution: &Solution) -> String {
+    let original_cost = solution
+        .evaluate(problem, None)
+        .0
+        .total_cost(problem.cost_config);
+    let mut report =
+        format!("Robustness to an instructor dropping out (from cost {original_cost:?}):\n");
+
+    let mut rows = Vec::new();
+    for instructor in problem.instructors {
+        let lost_sessions = problem
+            .sessions
+            .iter()
+            .filter(|session| {
+                solution.assignment[session.session_id.raw_index()]
+                    == Some(instructor.instructor_id)
+            })
+            .map(|session| session.session_id)
+            .collect::<Vec<_>>();
+        if lost_sessions.is_empty() {
+            continue;
+        }
+
+        let mut availabilities = problem.availabilities.clone();
+        for session in problem.sessions {
+            availabilities.set_availability(
+                session.session_id,
+                instructor.instructor_id,
+                Availability::Impossible,
+            );
+        }
+        // their locked sessions have to be reassigned too, so unlock them
+        let unlocked_sessions = lost_sessions
+            .iter()
+            .filter(|session_id| !problem.unlocked_sessions.contains(session_id))
+            .chain(problem.unlocked_sessions)
+            .copied()
+            .collect::<Vec<_>>();
+        let scenario = Problem {
+            availabilities: &availabilities,
+            unlocked_sessions: &unlocked_sessions,
+            ..problem
+        };
+
+        let mut repaired = solution.clone();
+        for session_id in &lost_sessions {
+            repaired.assignment[session_id.raw_index()] = None;
+        }
+        let repaired_cost = repaired
+            .evaluate(scenario, None)
+            .0
+            .total_cost(problem.cost_config)
+            .map(|cost| polish_solution(scenario, &mut repaired, cost));
+        let num_reassigned = lost_sessions
+            .iter()
+            .filter(|session_id| repaired.assignment[session_id.raw_index()].is_some())
+            .count();
+        let num_changed = repaired
+            .assignment
+            .iter()
+            .zip(solution.assignment.iter())
+            .filter(|(new, old)| new != old && old.is_some_and(|id| id != instructor.instructor_id))
+            .count();
+
+        rows.push((
+            repaired_cost,
+            instructor,
+            lost_sessions.len(),
+            num_reassigned,
+            num_changed,
+        ));
+    }
+
+    // the most fragile first, with infinite costs at the top
+    rows.sort_by_key(|&(cost, ..)| std::cmp::Reverse(cost.unwrap_or(CostValue::MAX)));
+    for (repaired_cost, instructor, num_lost, num_reassigned, num_changed) in rows {
+        let increase = match (repaired_cost, original_cost) {
+            (Some(new), Some(old)) => format!("+{}", new.saturating_sub(old)),
+            _ => String::from("infeasible"),
+        };
+        writeln!(
+            report,
+            "    {} ({}): {increase} to {repaired_cost:?}, {num_reassigned}/{num_lost} of their sessions reassigned, {num_changed} other sessions changed",
+            instructor.name, instructor.zid
+        )
+        .unwrap();
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acceptance_rate(cost_diff: f32, temperature: f32, rng: &mut SolverRng) -> f64 {
+        let num_trials = 10_000;
+        let num_accepted = (0..num_trials)
+            .filter(|_| accept(cost_diff, temperature, 1.0, rng))
+            .count();
+        num_accepted as f64 / num_trials as f64
+    }
+
+    #[test]
+    fn accepts_changes_which_are_not_worse() {
+        let mut rng = SolverRng::with_seed(0, true);
+        for cost_diff in [0.0, -1.0, -1000.0] {
+            for temperature in [0.1, 1.0, 5000.0] {
+                assert!((0..1000).all(|_| accept(cost_diff, temperature, 1.0, &mut rng)));
+            }
+        }
+    }
+
+    #[test]
+    fn acceptance_falls_as_cost_diff_over_temperature_rises() {
+        let mut rng = SolverRng::with_seed(0, true);
+        let rates =
+            [0.1, 0.5, 1.0, 2.0, 5.0].map(|ratio| acceptance_rate(ratio * 100.0, 100.0, &mut rng));
+
+        for (rate, next_rate) in rates.iter().zip(&rates[1..]) {
+            assert!(
+                rate > next_rate,
+                "acceptance rates {rates:?} should decrease"
+            );
+        }
+        // exp(-0.1) and exp(-5), give or take sampling noise
+        assert!((rates[0] - 0.905).abs() < 0.03, "{rates:?}");
+        assert!(rates[4] < 0.02, "{rates:?}");
+    }
+
+    #[test]
+    fn equal_costs_still_draw_from_the_rng() {
+        let mut rng = SolverRng::with_seed(0, true);
+        let mut expected_rng = SolverRng::with_seed(0, true);
+        assert!(accept(0.0, 1.0, 1.0, &mut rng));
+        expected_rng.f32();
+        assert_eq!(rng.f32(), expected_rng.f32());
+    }
+}
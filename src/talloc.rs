@@ -1,15 +1,17 @@
 use std::{
     collections::HashMap,
     fs::{self, File},
-    io::{self, Write},
-    path::Path,
+    io::{self, BufReader, Write},
+    path::{Path, PathBuf},
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 use reqwest::blocking::Client;
+use serde::{Deserialize, Deserializer};
 
 use crate::{
     classes::Mode,
+    tsv::Tsv,
     utils::{Day, TimeOfDay},
 };
 
@@ -35,7 +37,7 @@ fn read_jwt() -> Result<String> {
     Ok(jwt)
 }
 
-fn make_request(client: &Client, endpoint: &str) -> Result<serde_json::Value> {
+fn send_request(client: &Client, endpoint: &str) -> Result<reqwest::blocking::Response> {
     let jwt = read_jwt().with_context(|| {
         "could not get JWT for talloc auth.\n".to_string()
             + "Hint: you should get a talloc token from\n"
@@ -43,15 +45,18 @@ fn make_request(client: &Client, endpoint: &str) -> Result<serde_json::Value> {
             + "file `jwt` in your current working directory."
     })?;
 
-    let response = client
+    client
         .get(endpoint)
         .header("x-jwt-auth", jwt)
         .header("Accept", "application/json")
         .send()
         .and_then(|response| response.error_for_status())
-        .with_context(|| anyhow!("failed to fetch {endpoint}"))?;
+        .with_context(|| anyhow!("failed to fetch {endpoint}"))
+}
 
-    serde_json::from_reader(response).context("failed to decode talloc response as json")
+fn make_request(client: &Client, endpoint: &str) -> Result<serde_json::Value> {
+    serde_json::from_reader(send_request(client, endpoint)?)
+        .context("failed to decode talloc response as json")
 }
 
 pub fn extract_talloc_term_id(term_info: serde_json::Value) -> Result<String> {
@@ -66,101 +71,309 @@ pub fn extract_talloc_term_id(term_info: serde_json::Value) -> Result<String> {
     Ok(term_id.to_string())
 }
 
-fn fetch_applications_value(json_cache: &Path) -> Result<serde_json::Value> {
+// Makes sure the applications for the given term (or the current term if it's None)
+// are cached at `json_cache`, downloading them straight to the file if not
+fn fetch_applications(
+    json_cache: &Path,
+    term_id: Option<&str>,
+    allow_download: bool,
+) -> Result<()> {
     if json_cache.exists() {
         println!("Using cached talloc download at {}", json_cache.display());
+        return Ok(());
+    } else if !allow_download {
+        bail!(
+            "no cached talloc download at {} and downloading is disabled",
+            json_cache.display()
+        )
+    }
 
-        let cache_file = File::open(json_cache).with_context(|| {
-            anyhow!(
-                "failed to read cache of talloc applications at {}",
-                json_cache.display()
-            )
-        })?;
-        serde_json::from_reader(cache_file).with_context(|| {
-            anyhow!(
-                "failed to parse cache of talloc applications at {}",
-                json_cache.display()
-            )
-        })
-    } else {
-        let client = reqwest::blocking::Client::new();
+    let client = reqwest::blocking::Client::new();
 
-        let term_id = extract_talloc_term_id(
+    let term_id = match term_id {
+        Some(term_id) => {
+            println!("Using talloc applications from term code {term_id}");
+            term_id.to_string()
+        }
+        None => extract_talloc_term_id(
             make_request(&client, talloc_api_current_term_endpoint())
                 .context("failed to fetch term_info")?,
-        )?;
+        )?,
+    };
 
-        print!("Downloading talloc applications, this may take a while... ");
-        _ = io::stdout().flush();
-        let applications = make_request(&client, &talloc_api_applications_endpoint(&term_id))?;
-        println!("done!");
+    println!(
+        "No talloc cache at {}, so every application for this term will be downloaded",
+        json_cache.display()
+    );
+    print!("Downloading talloc applications, this may take a while... ");
+    _ = io::stdout().flush();
+    let mut response = send_request(&client, &talloc_api_applications_endpoint(&term_id))?;
 
-        fs::write(
-            json_cache,
-            serde_json::to_string(&applications)
-                .expect("should be able to re-serialise what we just deserialised"),
+    // write to a temporary file first so an interrupted download isn't used as the cache
+    let partial_cache = json_cache.with_extension("json.partial");
+    let mut write_cache = || -> Result<()> {
+        let mut cache_file = File::create(&partial_cache)?;
+        response.copy_to(&mut cache_file)?;
+        fs::rename(&partial_cache, json_cache)?;
+        Ok(())
+    };
+    write_cache().with_context(|| {
+        anyhow!(
+            "failed to write cache of talloc download at {}",
+            json_cache.display()
         )
+    })?;
+    println!("done!");
+    println!("Cached download to {}", json_cache.display());
+
+    Ok(())
+}
+
+// Only the availability slots (keys like `mon09`) of each application are kept,
+// so the rest of the (potentially very large) JSON is never held in memory
+fn is_availability_key(key: &str) -> bool {
+    key.len() == 5
+        && key.is_char_boundary(3)
+        && key[..3].parse::<Day>().is_ok()
+        && key[3..].bytes().all(|byte| byte.is_ascii_digit())
+}
+
+fn deserialize_availability_slots<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<serde_json::Value>, D::Error> {
+    let Some(application) =
+        Option::<HashMap<String, serde_json::Value>>::deserialize(deserializer)?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(serde_json::Value::Object(
+        application
+            .into_iter()
+            .filter(|(key, _)| is_availability_key(key))
+            .collect(),
+    )))
+}
+
+#[derive(Deserialize)]
+struct TallocProfile {
+    zid: String,
+}
+
+#[derive(Deserialize)]
+struct TallocApplicant {
+    profile: TallocProfile,
+    #[serde(default, deserialize_with = "deserialize_availability_slots")]
+    application: Option<serde_json::Value>,
+}
+
+// Parses the cached applications one applicant at a time, keyed by zid
+fn group_talloc_by_applicant(json_cache: &Path) -> Result<HashMap<String, serde_json::Value>> {
+    let cache_file = File::open(json_cache).with_context(|| {
+        anyhow!(
+            "failed to read cache of talloc applications at {}",
+            json_cache.display()
+        )
+    })?;
+    let applicants: Vec<TallocApplicant> = serde_json::from_reader(BufReader::new(cache_file))
         .with_context(|| {
             anyhow!(
-                "failed to write cache of talloc download at {}",
+                "failed to parse cache of talloc applications at {}",
                 json_cache.display()
             )
         })?;
-        println!("Cached download to {}", json_cache.display());
-
-        Ok(applications)
-    }
-}
-
-fn group_talloc_by_applicant(
-    raw_json: serde_json::Value,
-) -> Result<HashMap<String, serde_json::Value>> {
-    let applicants = match raw_json {
-        serde_json::Value::Array(arr) => arr,
-        _ => bail!("outer talloc JSON is not an array"),
-    };
 
     applicants
         .into_iter()
-        .map(|mut application| {
-            let zid = application
-                .pointer("/profile/zid")
-                .with_context(|| anyhow!("application is missing a zid"))?
-                .as_str()
-                .context("profile.zid is not a string")?
-                .to_string();
-            Ok((
-                zid.to_string(),
-                application
-                    .get_mut("application")
-                    .with_context(|| anyhow!("{zid} does not have an associated application"))?
-                    .take(),
-            ))
+        .map(|applicant| {
+            let zid = applicant.profile.zid;
+            let application = applicant
+                .application
+                .with_context(|| anyhow!("{zid} does not have an associated application"))?;
+            Ok((zid, application))
         })
         .collect()
 }
 
+// How to combine an instructor's availability when applications from several
+// terms are used. Terms where the instructor didn't apply are left out of the
+// combination entirely, and only an instructor with no application in any
+// term gets the `NoApplication` default.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum TermCombinePolicy {
+    Min,
+    Max,
+    Average,
+}
+
+impl TermCombinePolicy {
+    fn combine(
+        self,
+        availabilities: impl Iterator<Item = Option<Availability>>,
+    ) -> Option<Availability> {
+        let availabilities = availabilities.collect::<Option<Vec<_>>>()?;
+        match self {
+            TermCombinePolicy::Min => availabilities.into_iter().min(),
+            TermCombinePolicy::Max => availabilities.into_iter().max(),
+            TermCombinePolicy::Average => {
+                let num_terms = availabilities.len() as u32;
+                if num_terms == 0 {
+                    return None;
+                }
+                let total = availabilities
+                    .into_iter()
+                    .map(|availability| availability as u32)
+                    .sum::<u32>();
+                // round to the nearest level, with halves rounding up
+                Availability::from_level(((total + num_terms / 2) / num_terms) as u8)
+            }
+        }
+    }
+}
+
 pub struct TallocApps {
-    applications: HashMap<String, serde_json::Value>,
+    terms: Vec<HashMap<String, serde_json::Value>>,
+    combine_policy: TermCombinePolicy,
     ignore_no_application: bool,
 }
 
 impl TallocApps {
-    pub fn fetch(json_cache: &Path, ignore_no_application: bool) -> Result<Self> {
-        let raw_json = fetch_applications_value(json_cache)?;
+    // Uses the applications from each of `term_ids`, or from the current term
+    // if there aren't any. Each term is cached in its own file next to `json_cache`.
+    pub fn fetch(
+        json_cache: &Path,
+        term_ids: &[String],
+        combine_policy: TermCombinePolicy,
+        ignore_no_application: bool,
+        allow_download: bool,
+    ) -> Result<Self> {
+        let sources: Vec<(PathBuf, Option<&str>)> = if term_ids.is_empty() {
+            vec![(json_cache.to_path_buf(), None)]
+        } else {
+            let cache_stem = json_cache
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            term_ids
+                .iter()
+                .map(|term_id| {
+                    (
+                        json_cache.with_file_name(format!("{cache_stem}_{term_id}.json")),
+                        Some(term_id.as_str()),
+                    )
+                })
+                .collect()
+        };
+
+        let terms = sources
+            .into_iter()
+            .map(|(json_cache, term_id)| {
+                fetch_applications(&json_cache, term_id, allow_download)?;
+                group_talloc_by_applicant(&json_cache).with_context(|| "bad talloc JSON")
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(TallocApps {
+            terms,
+            combine_policy,
+            ignore_no_application,
+        })
+    }
+
+    // Reads availabilities from a survey instead of talloc, with a `zid` column and a
+    // column per slot named like talloc's (e.g. `mon09`) holding a level from 0
+    // (impossible) to 3 (preferred), or blank for impossible. The same level is
+    // used for face to face and online sessions.
+    pub fn from_survey_tsv(
+        survey_tsv: &Tsv,
+        combine_policy: TermCombinePolicy,
+        ignore_no_application: bool,
+    ) -> Result<Self> {
+        let slot_columns = survey_tsv
+            .header_fields()
+            .iter()
+            .filter(|field| field.as_str() != "zid")
+            .collect::<Vec<_>>();
+        for column in &slot_columns {
+            let is_slot = column.len() == 5
+                && column.is_char_boundary(3)
+                && column[..3].parse::<Day>().is_ok()
+                && column[3..]
+                    .parse::<u8>()
+                    .is_ok_and(|hour| TimeOfDay::from_hour(hour).is_some());
+            if !is_slot {
+                bail!(
+                    "unexpected column {column:?} in availability survey {}, expected slots like \"mon09\"",
+                    survey_tsv.path()
+                );
+            }
+        }
+
+        let mut applications = HashMap::new();
+        for row in survey_tsv {
+            let zid = row.get("zid")?.trim().to_string();
+            let mut application = serde_json::Map::new();
+            for column in &slot_columns {
+                let raw_level = row.get(column)?.trim();
+                let level = if raw_level.is_empty() {
+                    0
+                } else {
+                    raw_level
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|&level| Availability::from_level(level).is_some())
+                        .with_context(|| {
+                            anyhow!("bad availability level {raw_level:?} for {zid} at {column}, expected 0-3")
+                        })?
+                };
+                // talloc packs the face to face level in the low bits and online above it
+                application.insert(
+                    column.to_lowercase(),
+                    serde_json::Value::String((level | (level << 2)).to_string()),
+                );
+            }
+
+            if applications
+                .insert(zid.clone(), serde_json::Value::Object(application))
+                .is_some()
+            {
+                bail!("{zid} has more than one row in the availability survey");
+            }
+        }
 
         Ok(TallocApps {
-            applications: group_talloc_by_applicant(raw_json).with_context(|| "bad talloc JSON")?,
+            terms: vec![applications],
+            combine_policy,
             ignore_no_application,
         })
     }
 
+    #[cfg(test)]
+    pub fn from_applications(applications: HashMap<String, serde_json::Value>) -> Self {
+        TallocApps {
+            terms: vec![applications],
+            combine_policy: TermCombinePolicy::Min,
+            ignore_no_application: false,
+        }
+    }
+
     pub fn get_application<'a>(&'a self, zid: &str) -> Option<TallocApplication<'a>> {
-        match self.applications.get(zid) {
-            Some(application) => Some(TallocApplication::Application(application)),
-            None => self
+        let applications = self
+            .terms
+            .iter()
+            .filter_map(|term| term.get(zid))
+            .collect::<Vec<_>>();
+
+        match applications.len() {
+            0 => self
                 .ignore_no_application
                 .then_some(TallocApplication::NoApplication),
+            1 => Some(TallocApplication::Application(applications[0])),
+            _ => Some(TallocApplication::Combined(
+                applications,
+                self.combine_policy,
+            )),
         }
     }
 }
@@ -173,14 +386,27 @@ pub enum Availability {
     Preferred,
 }
 
+impl Availability {
+    pub fn from_level(level: u8) -> Option<Self> {
+        Some(match level {
+            0 => Availability::Impossible,
+            1 => Availability::Dislike,
+            2 => Availability::Possible,
+            3 => Availability::Preferred,
+            _ => return None,
+        })
+    }
+}
+
 // #[derive(Clone, Copy)]
 // pub struct TallocApplication<'a> {
 //     application: &'a serde_json::Value,
 // }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum TallocApplication<'a> {
     Application(&'a serde_json::Value),
+    Combined(Vec<&'a serde_json::Value>, TermCombinePolicy),
     NoApplication,
 }
 
@@ -190,6 +416,11 @@ impl TallocApplication<'_> {
 
         let application = match self {
             TallocApplication::Application(application) => application,
+            TallocApplication::Combined(applications, combine_policy) => {
+                return combine_policy.combine(applications.iter().map(|application| {
+                    TallocApplication::Application(application).get_availability(day, time, mode)
+                }))
+            }
             TallocApplication::NoApplication => return Some(Availability::Impossible),
         };
 
@@ -203,18 +434,12 @@ impl TallocApplication<'_> {
             raw_availability >>= 2;
         }
 
-        Some(match raw_availability & 0b11 {
-            0 => Availability::Impossible,
-            1 => Availability::Dislike,
-            2 => Availability::Possible,
-            3 => Availability::Preferred,
-            _ => return None,
-        })
+        Availability::from_level(raw_availability & 0b11)
     }
 
     pub fn is_default(&self) -> bool {
         match self {
-            TallocApplication::Application(_) => false,
+            TallocApplication::Application(_) | TallocApplication::Combined(..) => false,
             TallocApplication::NoApplication => true,
         }
     }
@@ -1,11 +1,11 @@
 // I already had this code lying around.. but serde might've been nicer here
 
-use std::{collections::HashMap, fs, ops::Range, path::Path};
+use std::{collections::HashMap, fmt, fs, ops::Range, path::Path};
 
 use anyhow::{anyhow, bail, Context, Result};
 
 pub struct Tsv {
-    _header_fields: Vec<String>,
+    header_fields: Vec<String>,
     header_to_index: HashMap<String, usize>,
     rows: Vec<Vec<String>>,
     path: String,
@@ -57,6 +57,11 @@ impl<'a> TsvRow<'a> {
 
         Ok(&self.tsv.rows[self.index][index])
     }
+
+    // The line in the file this row came from, counting the header as line 1
+    pub fn line_number(&self) -> usize {
+        self.index + 2
+    }
 }
 
 fn split_line(line: &str) -> Vec<String> {
@@ -64,6 +69,14 @@ fn split_line(line: &str) -> Vec<String> {
 }
 
 impl Tsv {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn header_fields(&self) -> &[String] {
+        &self.header_fields
+    }
+
     pub fn read_from_path(path: &Path) -> Result<Self> {
         let path_lossy = path.to_string_lossy();
 
@@ -74,6 +87,41 @@ impl Tsv {
             .with_context(|| anyhow!("could not parse {path_lossy} as a TSV"))
     }
 
+    // Builds a TSV in memory, e.g. for writing outputs, where `path` is
+    // only used in error messages
+    pub fn from_rows(path: &str, header_fields: &[&str], rows: Vec<Vec<String>>) -> Result<Self> {
+        for field in header_fields
+            .iter()
+            .copied()
+            .chain(rows.iter().flatten().map(String::as_str))
+        {
+            if field.contains(['\t', '\n', '\r']) {
+                bail!("TSV field {field:?} for {path} can't contain tabs or newlines");
+            }
+        }
+
+        for row in &rows {
+            if row.len() != header_fields.len() {
+                bail!(
+                    "row {row:?} has {} fields, but the header for {path} has {} fields",
+                    row.len(),
+                    header_fields.len()
+                )
+            }
+        }
+
+        Ok(Tsv {
+            header_fields: header_fields.iter().map(|&field| field.into()).collect(),
+            header_to_index: header_fields
+                .iter()
+                .enumerate()
+                .map(|(idx, &field)| (field.into(), idx))
+                .collect(),
+            rows,
+            path: path.into(),
+        })
+    }
+
     pub fn try_from_str(path: &str, value: &str) -> Result<Self> {
         let mut lines_iter = value.lines();
         let header = lines_iter.next().unwrap_or_default();
@@ -101,10 +149,70 @@ impl Tsv {
             .collect::<Result<_>>()?;
 
         Ok(Tsv {
-            _header_fields: header_fields,
+            header_fields,
             rows,
             header_to_index,
             path: path.into(),
         })
     }
 }
+
+// Writes the TSV back out in the same format `try_from_str` reads
+impl fmt::Display for Tsv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.header_fields.join("\t"))?;
+        for row in &self.rows {
+            writeln!(f, "{}", row.join("\t"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|&field| field.into()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_through_display() -> Result<()> {
+        let header_fields = ["zid", "name", "empty"];
+        let original_rows = rows(&[&["z5000000", "Ada Lovelace", ""], &["z5000001", "", "x"]]);
+
+        let written = Tsv::from_rows("test.tsv", &header_fields, original_rows.clone())?;
+        let read = Tsv::try_from_str("test.tsv", &written.to_string())?;
+
+        assert_eq!(read.header_fields, header_fields);
+        assert_eq!(read.rows, original_rows);
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_with_no_rows() -> Result<()> {
+        let written = Tsv::from_rows("test.tsv", &["a", "b"], Vec::new())?;
+        let read = Tsv::try_from_str("test.tsv", &written.to_string())?;
+
+        assert_eq!(read.header_fields, ["a", "b"]);
+        assert!(read.rows.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_tabs_and_newlines_in_cells() {
+        for bad_field in ["a\tb", "a\nb", "a\rb"] {
+            assert!(Tsv::from_rows("test.tsv", &["field"], rows(&[&[bad_field]])).is_err());
+            assert!(Tsv::from_rows("test.tsv", &[bad_field], Vec::new()).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_ragged_rows() {
+        assert!(Tsv::from_rows("test.tsv", &["a", "b"], rows(&[&["1"]])).is_err());
+        assert!(Tsv::from_rows("test.tsv", &["a", "b"], rows(&[&["1", "2", "3"]])).is_err());
+        assert!(Tsv::try_from_str("test.tsv", "a\tb\n1\n").is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{result, str::FromStr};
+use std::{fmt, result, str::FromStr};
 
 use anyhow::{bail, Result};
 
@@ -14,6 +14,33 @@ pub fn match_ignore_case<T: Copy>(input: &str, cases: &[(&[&str], T)]) -> Option
     None
 }
 
+// Case insensitive glob matching, where `*` matches any (possibly empty) run of characters
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    fn glob_matches_bytes(pattern: &[u8], text: &[u8]) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some((b'*', pattern_rest)) => {
+                (0..=text.len()).any(|skip| glob_matches_bytes(pattern_rest, &text[skip..]))
+            }
+            Some((expected, pattern_rest)) => {
+                text.split_first().is_some_and(|(actual, text_rest)| {
+                    expected.eq_ignore_ascii_case(actual)
+                        && glob_matches_bytes(pattern_rest, text_rest)
+                })
+            }
+        }
+    }
+
+    glob_matches_bytes(pattern.trim().as_bytes(), text.trim().as_bytes())
+}
+
+// Whether any of the comma separated globs in `patterns` match `text`
+pub fn matches_glob_list(patterns: &str, text: &str) -> bool {
+    patterns
+        .split(',')
+        .any(|pattern| glob_matches(pattern, text))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Day {
     Mon,
@@ -53,13 +80,29 @@ impl Day {
     }
 }
 
+impl fmt::Display for Day {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct TimeOfDay(u8);
 
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:00", self.0)
+    }
+}
+
 impl TimeOfDay {
     pub fn as_24_hours(self) -> u8 {
         self.0
     }
+
+    pub fn from_hour(hour: u8) -> Option<Self> {
+        (hour < 24).then_some(TimeOfDay(hour))
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -92,13 +135,33 @@ impl FromStr for TimeOfDay {
 }
 
 impl TimeOfDay {
-    pub fn add_hr(self, hour: u8) -> Self {
-        let new_time = self.0.saturating_add(hour);
-        assert!(new_time < 24);
-        TimeOfDay(new_time)
+    // Parses a time like "09:50", rounding it to the nearest multiple of
+    // `grid_minutes` (halves round up). The rounded time has to be on the hour, and
+    // without a grid only times on the hour are accepted.
+    pub fn parse_snapped(s: &str, grid_minutes: Option<u16>) -> Option<Self> {
+        let (hours, minutes) = s.split_once(':').unwrap_or((s, "00"));
+        let (hours, minutes) = (hours.parse::<u16>().ok()?, minutes.parse::<u16>().ok()?);
+        if hours >= 24 || minutes >= 60 {
+            return None;
+        }
+
+        let mut total_minutes = hours * 60 + minutes;
+        if let Some(grid_minutes) = grid_minutes.filter(|&grid| grid > 0) {
+            total_minutes = (total_minutes + grid_minutes / 2) / grid_minutes * grid_minutes;
+        }
+
+        if total_minutes % 60 != 0 {
+            return None;
+        }
+        Self::from_hour(u8::try_from(total_minutes / 60).ok()?)
+    }
+
+    // None if the new time would be past midnight
+    pub fn add_hr(self, hour: u8) -> Option<Self> {
+        Self::from_hour(self.0.checked_add(hour)?)
     }
 
-    pub fn add_duration(self, duration: SessionDuration) -> Self {
+    pub fn add_duration(self, duration: SessionDuration) -> Option<Self> {
         self.add_hr(duration.hours)
     }
 }